@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::coverage::box_area;
+use crate::random_walker::{Position, DIRECTIONS};
+use crate::walk_model::{RadioState, Transitions, WalkModel, YourFunctionMode};
+
+/// Was im Plot gegen die Schritte aufgetragen wird
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PlotQuantity{
+    Distance,
+    SquaredDistance,
+    DistinctSites,
+    RadiusOfGyration,
+    HullArea
+}
+
+/// Eine analytische Kurve, die im Plot mit dem Durchschnitt verglichen wird
+pub struct Prediction{
+    pub name: String,
+    pub f: Box<dyn Fn(f64) -> f64>,
+    /// Die Kurve wird erst ab diesem t gezeichnet, z.B. weil ln(t) vorher zu klein ist
+    pub from: f64
+}
+
+impl Prediction{
+    fn new(name: impl Into<String>, f: impl Fn(f64) -> f64 + 'static) -> Self
+    {
+        Self { name: name.into(), f: Box::new(f), from: 0.0 }
+    }
+
+    fn starting_at(mut self, from: f64) -> Self
+    {
+        self.from = from;
+        self
+    }
+
+    /// Eine waagrechte Linie für einen Wert, der sich nach langer Zeit einstellt
+    fn stationary(name: impl Into<String>, value: f64) -> Self
+    {
+        Self::new(name, move |_| value)
+    }
+}
+
+/// Alle analytischen Kurven für eine Schrittregel und eine Messgröße.
+///
+/// Ist nichts bekannt oder gilt eine Kurve nur eingeschränkt,
+/// steht das in `note`, damit es in der Legende angezeigt werden kann
+#[derive(Default)]
+pub struct Predictions{
+    pub curves: Vec<Prediction>,
+    pub note: Option<String>
+}
+
+impl Predictions{
+    fn curves(curves: Vec<Prediction>) -> Self
+    {
+        Self { curves, note: None }
+    }
+
+    fn unknown(reason: impl Into<String>) -> Self
+    {
+        Self { curves: Vec::new(), note: Some(reason.into()) }
+    }
+
+    fn with_note(mut self, note: impl Into<String>) -> Self
+    {
+        self.note.get_or_insert_with(|| note.into());
+        self
+    }
+}
+
+/// Mittelwert und Kovarianz eines Schritts, wenn die Schritte nicht vom Ort abhängen
+#[derive(Debug, Clone, Copy)]
+pub struct StepMoments{
+    pub mean: (f64, f64),
+    /// Kovarianz xx, yy, xy
+    pub covariance: (f64, f64, f64),
+    /// Jeder Schritt ist genauso wahrscheinlich wie der entgegengesetzte
+    pub symmetric: bool
+}
+
+impl StepMoments{
+    pub fn of(transitions: &Transitions) -> Self
+    {
+        let (mut mx, mut my, mut xx, mut yy, mut xy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        let mut probabilities: HashMap<(i32, i32), f64> = HashMap::new();
+        for &((dx, dy), p) in transitions{
+            let (x, y) = (dx as f64, dy as f64);
+            mx += p * x;
+            my += p * y;
+            xx += p * x * x;
+            yy += p * y * y;
+            xy += p * x * y;
+            *probabilities.entry((dx, dy)).or_insert(0.0) += p;
+        }
+        let symmetric = probabilities.iter()
+            .all(|(&(dx, dy), p)| (p - probabilities.get(&(-dx, -dy)).copied().unwrap_or(0.0)).abs() < 1e-12);
+        Self{
+            mean: (mx, my),
+            covariance: (xx - mx * mx, yy - my * my, xy - mx * my),
+            symmetric
+        }
+    }
+
+    pub fn drift(&self) -> f64
+    {
+        self.mean.0.hypot(self.mean.1)
+    }
+
+    /// Die Diffusionskonstante D mit ⟨r²⟩ = 4D·t ohne Drift
+    pub fn diffusion_constant(&self) -> f64
+    {
+        (self.covariance.0 + self.covariance.1) / 4.0
+    }
+
+    fn determinant(&self) -> f64
+    {
+        let (xx, yy, xy) = self.covariance;
+        xx * yy - xy * xy
+    }
+
+    fn eigenvalues(&self) -> (f64, f64)
+    {
+        let (xx, yy, xy) = self.covariance;
+        let mean = (xx + yy) / 2.0;
+        let root = (((xx - yy) / 2.0).powi(2) + xy * xy).sqrt();
+        (mean + root, (mean - root).max(0.0))
+    }
+
+    fn isotropic(&self) -> bool
+    {
+        let (a, b) = self.eigenvalues();
+        (a - b).abs() < 1e-12
+    }
+}
+
+/// Die Wahrscheinlichkeiten der Schritte, falls sie überall gleich sind.
+/// Es reicht, einen Ort aus jeder Vorzeichen-Klasse von x und y anzuschauen
+fn constant_transitions(model: &WalkModel) -> Result<Option<Transitions>, String>
+{
+    let origin = model.transition_probabilities(&Position { x: 0, y: 0 })?;
+    for x in -1..=1{
+        for y in -1..=1{
+            if model.transition_probabilities(&Position { x, y })? != origin {
+                return Ok(None);
+            }
+        }
+    }
+    Ok(Some(origin))
+}
+
+/// ⟨|X|⟩ für eine Gauß-Verteilung mit Mittelwert 0 und den Varianzen `a` und `b`
+/// entlang der Hauptachsen
+fn gaussian_mean_norm(a: f64, b: f64) -> f64
+{
+    const SAMPLES: usize = 360;
+    let angular = (0..SAMPLES)
+        .map(|i| 2.0 * PI * i as f64 / SAMPLES as f64)
+        .map(|phi| (a * phi.cos().powi(2) + b * phi.sin().powi(2)).sqrt())
+        .sum::<f64>() / SAMPLES as f64;
+    (PI / 2.0).sqrt() * angular
+}
+
+/// Mittlerer Abstand im Kasten, wenn alle Felder gleich wahrscheinlich sind
+fn uniform_box_distance(half_width: i32) -> f64
+{
+    let range = || -half_width..=half_width;
+    let sum: f64 = range()
+        .flat_map(|x| range().map(move |y| (x as f64).hypot(y as f64)))
+        .sum();
+    sum / box_area(half_width) as f64
+}
+
+/// Mittlerer Abstand im Gleichgewicht beim Bias pro Achse zum Ursprung hin.
+///
+/// x und y sind dann unabhängig voneinander mit P(|x| = k) ∝ ρ^k für k > 0,
+/// wobei ρ = (1/2 - s) / (1/2 + s). Für kleines s wird die Summe zu lang,
+/// dann wird mit zwei Laplace-Verteilungen gerechnet
+fn axis_bias_stationary_distance(strength_of_bias: f64) -> f64
+{
+    const MAX_TERMS: usize = 500;
+    let (p, q) = (0.5 + strength_of_bias, 0.5 - strength_of_bias);
+    let rho = q / p;
+    let decay = -rho.ln();
+    let terms = (40.0 / decay).ceil().max(1.0);
+    if terms > MAX_TERMS as f64 {
+        // ∫∫ e^(-|x|-|y|)·√(x²+y²) / 4 = 1 + ln(1+√2)/√2
+        let c = 1.0 + (1.0 + 2.0_f64.sqrt()).ln() / 2.0_f64.sqrt();
+        return c / decay;
+    }
+    let p0 = 2.0 * strength_of_bias / (1.0 + 2.0 * strength_of_bias);
+    // Wahrscheinlichkeit von |x| = k
+    let marginal: Vec<f64> = (0..=terms as usize)
+        .map(|k| if k == 0 { p0 } else { p0 / p * rho.powi(k as i32 - 1) })
+        .collect();
+    marginal.iter()
+        .enumerate()
+        .map(
+            |(k, pk)|
+            marginal.iter()
+                .enumerate()
+                .map(|(l, pl)| pk * pl * (k as f64).hypot(l as f64))
+                .sum::<f64>()
+        ).sum()
+}
+
+/// Die analytischen Kurven zu einer Schrittregel mit konstanten Schritten,
+/// z.B. dem normalen Random Walk, festen Richtungen oder Springerzügen.
+/// Nach dem zentralen Grenzwertsatz verhält sich der Walker für große t
+/// wie eine Brownsche Bewegung mit Drift
+fn constant_step_predictions(moments: StepMoments, quantity: PlotQuantity, nearest_neighbor: bool) -> Predictions
+{
+    let drift = moments.drift();
+    let d = moments.diffusion_constant();
+    let drifting = drift > 1e-12;
+    match quantity{
+        PlotQuantity::Distance if drifting => {
+            Predictions::curves(vec![Prediction::new(format!("Drift {drift:.3}·t"), move |t| drift * t)])
+        },
+        PlotQuantity::Distance => {
+            let (a, b) = moments.eigenvalues();
+            let factor = gaussian_mean_norm(a, b);
+            let name = if moments.isotropic() {
+                format!("√(π·D·t), D = {d:.3}")
+            } else {
+                format!("Gauß-Näherung, D = {d:.3}")
+            };
+            Predictions::curves(vec![Prediction::new(name, move |t| factor * t.sqrt())])
+        },
+        PlotQuantity::SquaredDistance => {
+            let name = if drifting {
+                format!("4D·t + v²·t², D = {d:.3}, v = {drift:.3}")
+            } else {
+                format!("4D·t, D = {d:.3}")
+            };
+            Predictions::curves(vec![Prediction::new(name, move |t| 4.0 * d * t + (drift * t).powi(2))])
+        },
+        // Für lange Walks gilt ⟨Rg²⟩ = 4D·t/6 wie bei einer idealen Polymerkette.
+        // Mit Drift kommt die gerade Strecke v·t dazu
+        PlotQuantity::RadiusOfGyration => {
+            let name = if drifting { "√(4D·t/6 + v²·t²/12)" } else { "√(4D·t/6)" };
+            Predictions::curves(vec![Prediction::new(name, move |t| (4.0 * d * t / 6.0 + (drift * t).powi(2) / 12.0).sqrt())])
+        },
+        PlotQuantity::DistinctSites | PlotQuantity::HullArea if drifting => {
+            Predictions::unknown("Mit Drift ist keine analytische Lösung bekannt")
+        },
+        // Asymptotik für rekurrente Walks in 2D: 2π·√det(Σ)·t / ln(t).
+        // Für kleine t ist ln(t) zu klein, daher erst ab t = 10
+        PlotQuantity::DistinctSites => {
+            let a = 2.0 * PI * moments.determinant().sqrt();
+            let mut curves = vec![
+                Prediction::new(format!("{:.2}·t / ln(t)", a), move |t| a * t / t.ln()).starting_at(10.0)
+            ];
+            if nearest_neighbor {
+                curves.push(Prediction::new("π·t / ln(8t)", |t| PI * t / (8.0 * t).ln()).starting_at(10.0));
+            }
+            Predictions::curves(curves)
+        },
+        // Mittlere Hüllenfläche der Brownschen Bewegung, π/2·√det(Σ)·t, isotrop π·D·t
+        PlotQuantity::HullArea => {
+            let a = PI / 2.0 * moments.determinant().sqrt();
+            let name = if moments.isotropic() { format!("π·D·t, D = {d:.3}") } else { format!("{a:.3}·t") };
+            Predictions::curves(vec![Prediction::new(name, move |t| a * t)])
+        }
+    }
+}
+
+/// Die Walker werden mit konstanter Geschwindigkeit `v` vom Ursprung weggetrieben
+fn ballistic_predictions(v: f64, quantity: PlotQuantity) -> Predictions
+{
+    match quantity{
+        PlotQuantity::Distance => Predictions::curves(vec![Prediction::new(format!("Drift {v:.3}·t"), move |t| v * t)]),
+        PlotQuantity::SquaredDistance => Predictions::curves(vec![Prediction::new(format!("({v:.3}·t)²"), move |t| (v * t).powi(2))]),
+        // Für große t ist die Spur fast eine gerade Strecke der Länge v·t
+        PlotQuantity::RadiusOfGyration => {
+            Predictions::curves(vec![Prediction::new(format!("{v:.3}·t / √12"), move |t| v * t / 12.0_f64.sqrt())])
+        },
+        PlotQuantity::DistinctSites | PlotQuantity::HullArea => {
+            Predictions::unknown("Mit Drift ist keine analytische Lösung bekannt")
+        }
+    }
+}
+
+/// Die Walker werden zum Ursprung gezogen und pendeln sich bei einem festen
+/// mittleren Abstand und Abstandsquadrat ein
+fn stationary_predictions(distance: f64, msd: f64, quantity: PlotQuantity, approximation: bool) -> Predictions
+{
+    let suffix = if approximation { " (Näherung)" } else { "" };
+    match quantity{
+        PlotQuantity::Distance => Predictions::curves(vec![Prediction::stationary(format!("Gleichgewicht {distance:.3}{suffix}"), distance)]),
+        PlotQuantity::SquaredDistance => Predictions::curves(vec![Prediction::stationary(format!("Gleichgewicht {msd:.3}{suffix}"), msd)]),
+        // Nach langer Zeit ist die Spur so verteilt wie der Walker im Gleichgewicht
+        PlotQuantity::RadiusOfGyration => {
+            let rg = msd.sqrt();
+            Predictions::curves(vec![Prediction::stationary(format!("√⟨r²⟩ im Gleichgewicht {rg:.3}{suffix}"), rg)])
+        },
+        PlotQuantity::DistinctSites | PlotQuantity::HullArea => {
+            Predictions::unknown("Für Walker mit Bias zum Ursprung ist keine analytische Lösung bekannt")
+        }
+    }
+}
+
+/// Die Skalierung des selbstabstoßenden Walkers.
+/// Die Vorfaktoren sind nicht bekannt, daher werden die
+/// Kurven am letzten Punkt `last_average` des Durchschnitts angepasst
+fn self_avoiding_predictions(one_dimensional: bool, quantity: PlotQuantity, last_average: Option<(f64, f64)>) -> Predictions
+{
+    let (squared, power) = match quantity{
+        PlotQuantity::Distance => (false, 1),
+        PlotQuantity::SquaredDistance => (true, 2),
+        _ => return Predictions::unknown("Für den selbstabstoßenden Walker ist keine analytische Lösung bekannt")
+    };
+    let fit = |f: &dyn Fn(f64) -> f64| {
+        match last_average {
+            Some((x, y)) if x > 1.0 => y / f(x).powi(power),
+            _ => 1.0
+        }
+    };
+    let curves = if one_dimensional {
+        let t_2_3 = |t: f64| t.powf(2.0 / 3.0);
+        let a = fit(&t_2_3);
+        let name = if squared { "Skalierung ~ t^(4/3)" } else { "Skalierung ~ t^(2/3)" };
+        vec![Prediction::new(name, move |t| a * t_2_3(t).powi(power))]
+    } else {
+        let log_corrected = |t: f64| (t * t.max(1.0).ln().sqrt()).sqrt();
+        let a = fit(&log_corrected);
+        let b = fit(&f64::sqrt);
+        let (name_a, name_b) = if squared {
+            ("Skalierung ~ t·ln(t)^(1/2)", "Skalierung ~ t")
+        } else {
+            ("Skalierung ~ (t·ln(t)^(1/2))^(1/2)", "Skalierung ~ t^(1/2)")
+        };
+        vec![
+            Prediction::new(name_a, move |t| a * log_corrected(t).powi(power)),
+            Prediction::new(name_b, move |t| b * t.sqrt().powi(power))
+        ]
+    };
+    Predictions::curves(curves)
+}
+
+/// Die analytischen oder asymptotischen Vorhersagen für die Schrittregel von `model`.
+///
+/// Für Schrittregeln ohne bekanntes Ergebnis sind keine Kurven dabei,
+/// stattdessen steht in `note` warum. `last_average` ist der letzte Punkt (t, Mittelwert)
+/// des Durchschnitts, an den Kurven mit unbekanntem Vorfaktor angepasst werden.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::analytical::{predictions, PlotQuantity};
+/// use girls_day::walk_model::{WalkModel, RadioState};
+/// let model = WalkModel::default();
+/// let msd = predictions(&model, PlotQuantity::SquaredDistance, None);
+/// assert!(((msd.curves[0].f)(100.0) - 100.0).abs() < 1e-9);
+/// let distance = predictions(&model, PlotQuantity::Distance, None);
+/// let expected = std::f64::consts::PI.sqrt() / 2.0 * 10.0;
+/// assert!(((distance.curves[0].f)(100.0) - expected).abs() < 1e-9);
+///
+/// // Die Springerzüge von "Deine Funktion" haben ⟨r²⟩ = 5t
+/// let knight = WalkModel { radio: RadioState::YourFunction, ..WalkModel::default() };
+/// let msd = predictions(&knight, PlotQuantity::SquaredDistance, None);
+/// assert!(((msd.curves[0].f)(100.0) - 500.0).abs() < 1e-9);
+///
+/// let interacting = WalkModel { radio: RadioState::Interacting, ..WalkModel::default() };
+/// let none = predictions(&interacting, PlotQuantity::Distance, None);
+/// assert!(none.curves.is_empty() && none.note.is_some());
+/// ```
+///
+/// Mit Bias zum Ursprung stellt sich ein Gleichgewicht ein, hier mit
+/// ⟨r²⟩ = 1 / (4s²), was die exakte Rechnung bestätigt. Weil x + y bei jedem Schritt
+/// zwischen gerade und ungerade wechselt, pendelt der mittlere Abstand um den Wert
+/// im Gleichgewicht - der ist der Mittelwert aus einem geraden und einem ungeraden t:
+/// ```
+/// use girls_day::analytical::{predictions, PlotQuantity};
+/// use girls_day::exact::exact_statistics;
+/// use girls_day::walk_model::{WalkModel, RadioState};
+/// let model = WalkModel { radio: RadioState::BiasedTowardsOrigin, strength_of_bias: 0.2, ..WalkModel::default() };
+/// let exact = exact_statistics(&model, 200).unwrap();
+/// for (quantity, values) in [(PlotQuantity::SquaredDistance, &exact.msd), (PlotQuantity::Distance, &exact.mean_distance)] {
+///     let prediction = predictions(&model, quantity, None);
+///     let both = (values[199] + values[200]) / 2.0;
+///     assert!(((prediction.curves[0].f)(200.0) - both).abs() < 1e-4);
+/// }
+/// ```
+pub fn predictions(model: &WalkModel, quantity: PlotQuantity, last_average: Option<(f64, f64)>) -> Predictions
+{
+    let s = model.strength_of_bias;
+    let predictions = match model.radio{
+        RadioState::Interacting => {
+            return Predictions::unknown("Für wechselwirkende Walker ist keine analytische Lösung bekannt");
+        },
+        RadioState::YourFunction if model.your_function_mode == YourFunctionMode::Script => {
+            return Predictions::unknown("Für eine eigene Regel ist keine analytische Lösung bekannt");
+        },
+        RadioState::YourFunction if model.your_function_mode == YourFunctionMode::Code => {
+            return Predictions::unknown("Für deinen eigenen Code ist keine analytische Lösung bekannt");
+        },
+        RadioState::TrueSelfAvoiding => self_avoiding_predictions(model.one_dimensional, quantity, last_average),
+        // Jede Achse bekommt die Hälfte der Schritte, die dort im Mittel 2s nach außen gehen
+        RadioState::BiasedAwayFromOrigin if s > 0.0 => ballistic_predictions(2.0_f64.sqrt() * s, quantity),
+        // Die radialen Gewichte 1 ± 2s·e·u ergeben einen Drift s entlang u
+        RadioState::RadialBiasAwayFromOrigin if s > 0.0 => ballistic_predictions(s, quantity),
+        RadioState::BiasedTowardsOrigin if s > 0.0 => {
+            stationary_predictions(axis_bias_stationary_distance(s), 1.0 / (4.0 * s * s), quantity, false)
+        },
+        // Im Kontinuum mit D = 1/4 und Drift s zum Ursprung ist die Dichte ∝ r·e^(-s·r/D)
+        RadioState::RadialBiasTowardsOrigin if s > 0.0 => {
+            stationary_predictions(1.0 / (2.0 * s), 3.0 / (8.0 * s * s), quantity, true)
+        },
+        _ => {
+            let transitions = match constant_transitions(model){
+                Ok(Some(transitions)) => transitions,
+                Ok(None) => return Predictions::unknown("Für Schritte, die vom Ort abhängen, ist keine analytische Lösung bekannt"),
+                Err(e) => return Predictions::unknown(e)
+            };
+            let moments = StepMoments::of(&transitions);
+            let nearest_neighbor = transitions.iter()
+                .all(|(step, p)| DIRECTIONS.contains(step) && (p - 0.25).abs() < 1e-12);
+            let mut predictions = constant_step_predictions(moments, quantity, nearest_neighbor);
+            if let Some(half_width) = model.boundary() {
+                // Symmetrische Schritte kommen genauso oft in ein Feld hinein wie heraus,
+                // daher ist im Kasten am Ende jedes Feld gleich wahrscheinlich
+                let l = half_width as f64;
+                let stationary = match quantity{
+                    _ if !moments.symmetric => None,
+                    PlotQuantity::Distance => Some(uniform_box_distance(half_width)),
+                    PlotQuantity::SquaredDistance => Some(2.0 * l * (l + 1.0) / 3.0),
+                    PlotQuantity::DistinctSites => Some(box_area(half_width) as f64),
+                    _ => None
+                };
+                match stationary{
+                    Some(value) => predictions.curves.push(Prediction::stationary(format!("Gleichgewicht im Kasten {value:.3}"), value)),
+                    None => predictions = predictions.with_note("Der Kasten ist nicht berücksichtigt")
+                }
+            }
+            return predictions;
+        }
+    };
+    if model.box_enabled {
+        predictions.with_note("Der Kasten ist nicht berücksichtigt")
+    } else {
+        predictions
+    }
+}
@@ -1,103 +1,346 @@
+use std::ops::Range;
+
 use eframe::epaint::*;
+use egui::{Context, Painter, TextureOptions};
 
 use crate::random_walker::RandomWalker;
 
-pub fn calc_mesh(
-    walker: &RandomWalker,
-    canvas_size: Rect,
-    zoom: f32,
-    color: Color32,
-    col1_grad: Color32,
-    color2: Color32
-) -> Mesh
-{
-    let mut mesh = Mesh::default();
+/// Wie die Felder einer Richtung (x oder y) auf Pixel und Zellen verteilt werden.
+///
+/// Ist ein Feld mindestens ein Pixel breit, ist jede Zelle ein Feld. Sonst ist jede
+/// Zelle ein Pixel, in dem mehrere Felder liegen. So gibt es nie mehr Zellen als Pixel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Axis{
+    pixels: usize,
+    zoom: f32
+}
 
-    let diff = canvas_size.max.to_vec2()
-        - canvas_size.min.to_vec2();
+impl Axis{
+    /// Pixel pro Feld
+    fn scale(&self) -> f32
+    {
+        self.pixels as f32 / self.zoom
+    }
 
-    let scale = diff / zoom;
+    fn cell_is_site(&self) -> bool
+    {
+        self.scale() >= 1.0
+    }
 
-    let origin = 
-        canvas_size.min.to_vec2()
-        + diff * Vec2 { x: 0.5, y: 0.5 };
+    /// Das erste Feld, das man noch sieht
+    fn first_site(&self) -> i32
+    {
+        (-self.zoom / 2.0).floor() as i32
+    }
 
-    let width_diff = scale * Vec2 { x: 1.0, y: 1.0 };
+    fn cells(&self) -> usize
+    {
+        if self.cell_is_site() {
+            ((self.zoom / 2.0).ceil() as i32 - self.first_site() + 1) as usize
+        } else {
+            self.pixels
+        }
+    }
 
-    let mut add_to_mesh = |x, y, col| {
+    /// Das Pixel, an dem das Feld `site` anfängt. Der Ursprung liegt in der Mitte
+    fn pixel(&self, site: i32) -> i32
+    {
+        (self.pixels as f32 / 2.0 + self.scale() * site as f32).floor() as i32
+    }
 
-        let x = origin.x + scale.x * x as f32;
-        let y = origin.y + scale.y * y as f32;
+    fn cell(&self, site: i32) -> Option<usize>
+    {
+        let cell = if self.cell_is_site() {
+            site - self.first_site()
+        } else {
+            self.pixel(site)
+        };
+        usize::try_from(cell).ok().filter(|cell| *cell < self.cells())
+    }
 
-        let x_max = x + width_diff.x;
-        let y_max = y + width_diff.y;
+    /// Die Pixel, die zur Zelle gehören. Jedes Feld bekommt mindestens ein Pixel
+    fn pixels_of(&self, cell: usize) -> Range<usize>
+    {
+        if !self.cell_is_site() {
+            return cell..cell + 1;
+        }
+        let site = cell as i32 + self.first_site();
+        let start = self.pixel(site);
+        let end = self.pixel(site + 1).max(start + 1);
+        let clamp = |p: i32| p.clamp(0, self.pixels as i32) as usize;
+        clamp(start)..clamp(end)
+    }
+}
 
-        let min = Pos2{
-            x,
-            y
-        };
+/// Die Farbe zwischen `color` (p = 0) und `col1_grad` (p = 1)
+fn gradient(color: Color32, col1_grad: Color32, p: f32) -> Color32
+{
+    let mix = |a: u8, b: u8| (a as f32 + p * (b as i16 - a as i16) as f32) as u8;
+    Color32::from_rgb(
+        mix(color.r(), col1_grad.r()),
+        mix(color.g(), col1_grad.g()),
+        mix(color.b(), col1_grad.b())
+    )
+}
 
-        let max = Pos2{
-            x: x_max,
-            y: y_max
-        };
+/// Eine Zelle, in der der Walker noch nicht war
+const EMPTY: u32 = u32::MAX;
+
+/// Bei welchem Schritt der Farbverlauf einer Spur mit `drawn` Schritten endet:
+/// `drawn` aufgerundet auf ein Vielfaches der nächsten Zweierpotenz / 128, also
+/// knapp 1 % bis 1,6 % darüber. So ändert sich der Verlauf nur selten, und meistens
+/// müssen nur die neuen Zellen gefärbt und hochgeladen werden.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::animation::palette_end;
+/// assert_eq!(palette_end(100), 100);
+/// assert_eq!(palette_end(1001), 1008);
+/// assert_eq!(palette_end(1024), 1024);
+/// assert_eq!(palette_end(1025), 1040);
+/// ```
+pub fn palette_end(drawn: usize) -> usize
+{
+    let quantum = (drawn.next_power_of_two() / 128).max(1);
+    (drawn + quantum - 1) / quantum * quantum
+}
 
-        mesh.add_colored_rect(
-            Rect{
-                min, 
-                max
-            }, 
-            col
-        );
-    };
+/// Die Spur eines Walkers als Bild.
+///
+/// Statt für jeden Schritt ein Rechteck zu zeichnen, merkt sich ein Gitter für jede
+/// Zelle den Schritt des letzten Besuchs. Daraus wird ein Bild mit einem Pixel pro
+/// Bildschirm-Pixel gemalt, das egui als Textur anzeigt. Wie teuer das Anzeigen ist,
+/// hängt also von der Größe des Bildes ab und nicht davon, wie lang der Walk ist.
+/// Nur [`TrailImage::redraw`] geht einmal durch die ganze History.
+///
+/// Die Farbe einer Zelle ergibt sich erst beim Färben aus ihrem Schritt: Der erste
+/// Schritt bekommt `color`, der Verlauf endet mit `col1_grad` kurz nach dem letzten
+/// Schritt (siehe [`palette_end`]). Erst wenn der Walker darüber hinaus läuft, verschiebt
+/// sich der Verlauf und das ganze Bild wird neu gefärbt, sonst nur die geänderten Zellen.
+/// Gleiche Schritte ergeben deshalb das gleiche Bild, egal wie oft dazwischen neu
+/// gezeichnet wurde.
+///
+/// Beispiel:
+/// ```
+/// use egui::{Color32, Rect, pos2, vec2};
+/// use girls_day::animation::TrailImage;
+/// use girls_day::random_walker::RandomWalker;
+/// let canvas = Rect::from_min_size(pos2(0.0, 0.0), vec2(200.0, 200.0));
+/// let (color, col1_grad) = (Color32::from_rgb(80, 0, 161), Color32::from_rgb(254, 42, 42));
+/// let mut walker = RandomWalker::new(4);
+/// let mut every_frame = TrailImage::default();
+/// let mut once = TrailImage::default();
+/// once.redraw(&walker, canvas, 1.0, 50.0);
+/// for _ in 0..100 {
+///     for _ in 0..37 {
+///         walker.random_step();
+///     }
+///     every_frame.redraw(&walker, canvas, 1.0, 50.0);
+///     every_frame.tint(color, col1_grad);
+///     once.update(&walker);
+///     once.tint(color, col1_grad);
+/// }
+/// assert_eq!(every_frame.image().unwrap().pixels, once.image().unwrap().pixels);
+/// ```
+#[derive(Clone, Default)]
+pub struct TrailImage{
+    x: Axis,
+    y: Axis,
+    pixels_per_point: f32,
+    /// Der Schritt des letzten Besuchs jeder Zelle, [`EMPTY`] wenn sie noch nicht besucht wurde
+    cells: Vec<u32>,
+    image: Option<ColorImage>,
+    texture: Option<TextureHandle>,
+    /// So viele Einträge der History sind schon im Gitter
+    drawn: usize,
+    /// Die Zellen, die sich seit dem letzten Färben geändert haben
+    dirty: Option<[Range<usize>; 2]>,
+    /// Mit welchem Ende des Verlaufs und welchen Farben das Bild zuletzt gefärbt wurde
+    tinted: Option<(usize, Color32, Color32)>,
+    /// Die Pixel, die noch hochgeladen werden müssen
+    upload: Option<[Range<usize>; 2]>,
+    /// Ob das ganze Bild neu hochgeladen werden muss
+    resized: bool
+}
+
+impl std::fmt::Debug for TrailImage{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrailImage")
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .field("drawn", &self.drawn)
+            .finish_non_exhaustive()
+    }
+}
 
-    let total = 1.0 / (walker.history.len() as f32);
+impl TrailImage{
+    /// Ob das Gitter für diese Leinwand, diesen Zoom und die History des Walkers gefüllt wurde.
+    /// Wenn nicht, muss [`TrailImage::redraw`] aufgerufen werden
+    pub fn fits(&self, walker: &RandomWalker, canvas: Rect, pixels_per_point: f32, zoom: f32) -> bool
+    {
+        let (x, y) = Self::axes(canvas, pixels_per_point, zoom);
+        self.image.is_some()
+            && (x, y) == (self.x, self.y)
+            && self.pixels_per_point == pixels_per_point
+            && self.drawn <= walker.history.len()
+    }
 
-    let red_dist = (col1_grad.r() as i16 - color.r() as i16) as f32;
-    let blue_dist = (col1_grad.b() as i16 - color.b() as i16) as f32;
-    let green_dist = (col1_grad.g() as i16 - color.g() as i16) as f32;
+    fn axes(canvas: Rect, pixels_per_point: f32, zoom: f32) -> (Axis, Axis)
+    {
+        let pixels = |points: f32| ((points * pixels_per_point).round() as usize).max(1);
+        (
+            Axis { pixels: pixels(canvas.width()), zoom },
+            Axis { pixels: pixels(canvas.height()), zoom }
+        )
+    }
 
-    let r = color.r() as f32;
-    let b = color.b() as f32;
-    let g = color.g() as f32;
+    /// So viele Einträge der History sind schon im Gitter
+    pub fn drawn_steps(&self) -> usize
+    {
+        self.drawn
+    }
 
+    /// Das zuletzt gefärbte Bild
+    pub fn image(&self) -> Option<&ColorImage>
+    {
+        self.image.as_ref()
+    }
 
-    for (i, pos) in walker.history.vec.iter().enumerate()
+    /// Füllt das Gitter neu mit der ganzen Spur
+    pub fn redraw(&mut self, walker: &RandomWalker, canvas: Rect, pixels_per_point: f32, zoom: f32)
     {
-        let p = i as f32 * total;
-        let red: u8 = (r + p * red_dist) as u8;
-        let green = (g + p * green_dist) as u8;
-        let blue = (b + p * blue_dist) as u8;
+        let (x, y) = Self::axes(canvas, pixels_per_point, zoom);
+        let size = [x.pixels, y.pixels];
+        if self.image.as_ref().map(|image| image.size) != Some(size) {
+            self.image = Some(ColorImage::new(size, Color32::TRANSPARENT));
+            self.resized = true;
+        }
+        self.x = x;
+        self.y = y;
+        self.pixels_per_point = pixels_per_point;
+        self.cells.clear();
+        self.cells.resize(x.cells() * y.cells(), EMPTY);
+        self.drawn = 0;
+        self.update(walker);
+        self.dirty = Some([0..x.cells(), 0..y.cells()]);
+    }
 
-        let col = Color32::from_rgb(red, green, blue);
+    /// Nimmt die Schritte dazu, die seit dem letzten Mal dazugekommen sind
+    pub fn update(&mut self, walker: &RandomWalker)
+    {
+        for (pos, step) in walker.history.positions(self.drawn).zip(self.drawn..)
+        {
+            self.set_cell(pos.x, pos.y, step as u32);
+        }
+        self.drawn = walker.history.len();
+    }
 
-        add_to_mesh(
-            pos.x,
-            pos.y,
-            col
-        );
+    fn set_cell(&mut self, x: i32, y: i32, step: u32)
+    {
+        let (Some(cx), Some(cy)) = (self.x.cell(x), self.y.cell(y)) else {
+            return;
+        };
+        self.cells[cy * self.x.cells() + cx] = step;
+        let dirty = self.dirty.get_or_insert([cx..cx + 1, cy..cy + 1]);
+        for (range, c) in dirty.iter_mut().zip([cx, cy]) {
+            range.start = range.start.min(c);
+            range.end = range.end.max(c + 1);
+        }
     }
 
-    add_to_mesh(
-        walker.ort.x,
-        walker.ort.y,
-        color2
-    );
+    /// Färbt das Bild mit dem Verlauf von `color` zu `col1_grad`. Hat sich das Ende des
+    /// Verlaufs oder eine Farbe geändert, wird alles neu gefärbt, sonst nur die geänderten Zellen
+    pub fn tint(&mut self, color: Color32, col1_grad: Color32)
+    {
+        let Some(image) = &mut self.image else {
+            return;
+        };
+        let end = palette_end(self.drawn);
+        let palette = (end, color, col1_grad);
+        if self.tinted != Some(palette) {
+            self.dirty = Some([0..self.x.cells(), 0..self.y.cells()]);
+            self.tinted = Some(palette);
+        }
+        let Some([cells_x, cells_y]) = self.dirty.take() else {
+            return;
+        };
+        let total = 1.0 / (end as f32);
+        let width = image.size[0];
+        for cy in cells_y.clone() {
+            for py in self.y.pixels_of(cy) {
+                for cx in cells_x.clone() {
+                    let step = self.cells[cy * self.x.cells() + cx];
+                    let color = if step == EMPTY {
+                        Color32::TRANSPARENT
+                    } else {
+                        gradient(color, col1_grad, step as f32 * total)
+                    };
+                    let row = py * width;
+                    let pixels = self.x.pixels_of(cx);
+                    image.pixels[row + pixels.start..row + pixels.end].fill(color);
+                }
+            }
+        }
+        let pixels_x = self.x.pixels_of(cells_x.start).start..self.x.pixels_of(cells_x.end - 1).end;
+        let pixels_y = self.y.pixels_of(cells_y.start).start..self.y.pixels_of(cells_y.end - 1).end;
+        let upload = self.upload.get_or_insert([pixels_x.clone(), pixels_y.clone()]);
+        for (range, new) in upload.iter_mut().zip([pixels_x, pixels_y]) {
+            range.start = range.start.min(new.start);
+            range.end = range.end.max(new.end);
+        }
+    }
 
-    mesh
+    /// Lädt die mit [`TrailImage::tint`] geänderten Pixel hoch und zeigt das Bild auf `canvas`.
+    /// Darüber kommt die aktuelle Position des Walkers in `color2`
+    pub fn paint(&mut self, ctx: &Context, painter: &Painter, canvas: Rect, walker: &RandomWalker, color2: Color32)
+    {
+        let Some(image) = &self.image else {
+            return;
+        };
+        if let Some([pixels_x, pixels_y]) = self.upload.take() {
+            match &mut self.texture{
+                Some(texture) if !self.resized => {
+                    if !pixels_x.is_empty() && !pixels_y.is_empty() {
+                        let width = image.size[0];
+                        let top = pixels_y.start;
+                        let mut region = ColorImage::new([pixels_x.len(), pixels_y.len()], Color32::TRANSPARENT);
+                        for (row, py) in pixels_y.enumerate() {
+                            let source = py * width + pixels_x.start;
+                            region.pixels[row * pixels_x.len()..(row + 1) * pixels_x.len()]
+                                .copy_from_slice(&image.pixels[source..source + pixels_x.len()]);
+                        }
+                        texture.set_partial([pixels_x.start, top], region, TextureOptions::NEAREST);
+                    }
+                },
+                Some(texture) => texture.set(image.clone(), TextureOptions::NEAREST),
+                None => self.texture = Some(ctx.load_texture("walker_trail", image.clone(), TextureOptions::NEAREST))
+            }
+            self.resized = false;
+        }
+        if let Some(texture) = &self.texture {
+            painter.image(
+                texture.id(),
+                canvas,
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                Color32::WHITE
+            );
+        }
 
+        let scale = canvas.size() / self.x.zoom;
+        let min = canvas.center() + scale * Vec2::new(walker.ort.x as f32, walker.ort.y as f32);
+        painter.rect_filled(Rect::from_min_size(min, scale), 0.0, color2);
+    }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn update_mesh(
-    mesh: &mut Mesh,
-    old_total: usize,
-    walker: &RandomWalker,
+/// Ein kleiner Punkt für die aktuelle Position jedes Walkers
+pub fn walker_positions_mesh(
+    walkers: &[RandomWalker],
     canvas_size: Rect,
     zoom: f32,
-    color2: Color32
-)
+    color: Color32
+) -> Mesh
 {
+    let mut mesh = Mesh::default();
 
     let diff = canvas_size.max.to_vec2()
         - canvas_size.min.to_vec2();
@@ -108,50 +351,23 @@ pub fn update_mesh(
         canvas_size.min.to_vec2()
         + diff * Vec2 { x: 0.5, y: 0.5 };
 
-    let width_diff = scale * Vec2 { x: 1.0, y: 1.0 };
-
-    let mut add_to_mesh = |x, y, col| {
-
-        let x = origin.x + scale.x * x as f32;
-        let y = origin.y + scale.y * y as f32;
-
-        let x_max = x + width_diff.x;
-        let y_max = y + width_diff.y;
+    // Mindestens 3 Pixel groß, damit man die Walker auch bei kleinem Zoom sieht
+    let half_width = (scale.x.max(3.0)) / 2.0;
 
-        let min = Pos2{
-            x,
-            y
+    for walker in walkers{
+        let center = Pos2{
+            x: origin.x + scale.x * (walker.ort.x as f32 + 0.5),
+            y: origin.y + scale.y * (walker.ort.y as f32 + 0.5)
         };
-
-        let max = Pos2{
-            x: x_max,
-            y: y_max
-        };
-
-        mesh.add_colored_rect(
-            Rect{
-                min, 
-                max
-            }, 
-            col
-        );
-    };
-
-    for pos in walker.history.vec[old_total-1..].iter()
-    {
-        add_to_mesh(
-            pos.x,
-            pos.y,
-            color2
-        );
+        if canvas_size.contains(center){
+            mesh.add_colored_rect(
+                Rect::from_center_size(center, Vec2::splat(2.0 * half_width)), 
+                color
+            );
+        }
     }
 
-    add_to_mesh(
-        walker.ort.x,
-        walker.ort.y,
-        color2
-    );
-
+    mesh
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -160,68 +376,32 @@ pub enum PerformanceHint{
     PrioritizeOptics
 }
 
+/// Merkt sich, ob die Spur neu gezeichnet werden muss, z.B. weil ein anderer Walker
+/// in den Fokus kam
 #[derive(Clone, Debug)]
 pub struct MeshChangeTracker{
-    changed: bool,
-    old_steps: usize,
-    accumulated_change: usize
+    changed: bool
 }
 
 impl MeshChangeTracker{
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self{
-            changed: true,
-            old_steps: 0,
-            accumulated_change: 0
+            changed: true
         }
     }
 
-    pub fn reset(&mut self){
-        self.changed = true;
-        self.old_steps = 0;
-    }
-
     pub fn request_redraw(&mut self)
     {
         self.changed = true;
     }
 
-    pub fn redraw_finished(&mut self, steps: usize){
+    pub fn redraw_finished(&mut self){
         self.changed = false;
-        self.accumulated_change = 0;
-        self.old_steps = steps;
     }
 
-    pub fn check_if_needs_redraw(
-        &self, 
-        speed: f64,
-        priority: PerformanceHint
-    ) -> bool
+    pub fn check_if_needs_redraw(&self) -> bool
     {
-        if self.changed{
-            true
-        } else {
-            match priority{
-                PerformanceHint::PrioritizeOptics => {
-                    self.accumulated_change > 0
-                },
-                _ => {
-                    let speed = (speed * 18.0) as usize;
-                    let threshold = speed.max(10);
-                    self.accumulated_change > threshold
-                }
-            }
-        }
-    }
-
-    pub fn new_steps(&mut self, steps: usize){
-        let diff = steps - self.old_steps;
-        self.old_steps = steps;
-        self.accumulated_change += diff;
+        self.changed
     }
-
-    pub fn get_current_step(&self) -> usize {
-        self.old_steps
-    }
-}
\ No newline at end of file
+}
@@ -7,23 +7,47 @@ use egui::{
         Vec2,
         Sense
     }, 
-    Mesh, 
     plot::*, 
     Button,
     Visuals
 };
-use rand::SeedableRng;
-use rand_pcg::Pcg64;
-use rayon::prelude::*;
-use crate::animation::{MeshChangeTracker, PerformanceHint};
-use crate::random_walker::{RandomWalker, AverageDistance};
+use crate::animation::{MeshChangeTracker, PerformanceHint, TrailImage};
+use crate::random_walker::{RandomWalker, AverageDistance, History, StepRecord, swarm_radius};
+use crate::power_law::{fit_power_law, log_log, log_axis_label};
+use crate::walk_model::{WalkModel, RadioState};
+use crate::ensemble::{EnsembleView, create_walkers, create_streaming_walkers};
+use crate::coverage::Coverage;
+use crate::first_passage::FirstPassage;
+use crate::observable::ObservablePanel;
+use crate::arcsine::ArcsineView;
+use crate::winding::WindingView;
+use crate::runs::RepeatedRuns;
+use crate::comparison::{Comparison, CanvasStyle};
+use crate::sweep::Sweep;
+use crate::large_deviation::LargeDeviation;
+use crate::wang_landau::WangLandau;
+use crate::exact::ExactOverlay;
+use crate::analytical::{predictions, PlotQuantity};
+
+/// Höchstes Schritt-limit und höchste Anzahl an Walkern, je nachdem ob alle Walker ihre Spur speichern
+const LIMITS: (u64, usize) = (500_000, 200);
+const STREAMING_LIMITS: (u64, usize) = (5_000_000, 50_000);
+/// Hinweis für die Ansichten, die im Streaming-Modus nicht gehen
+const NEEDS_TRAJECTORIES: &str = "Dafür braucht es die Spuren aller Walker. Im Streaming-Modus speichert nur der Walker im Fokus seine Spur";
 
 #[derive(PartialEq)]
-pub enum RadioState{
-    NoBias,
-    BiasedTowardsOrigin,
-    BiasedAwayFromOrigin,
-    YourFunction
+pub enum CentralView{
+    Walker,
+    Ensemble,
+    FirstPassage,
+    Observables,
+    Arcsine,
+    Winding,
+    Runs,
+    Comparison,
+    Sweep,
+    LargeDeviation,
+    WangLandau
 }
 
 #[derive(PartialEq)]
@@ -40,17 +64,39 @@ pub struct TemplateApp {
     speed: f64,
     current_time: f64,
     zoom: f32,
-    old_mesh: Option<Mesh>,
+    trail: TrailImage,
     step_limit: u64,
     seed: u64,
     display_walker_id: usize,
     num_of_walkers: usize,
+    /// Ob beim nächsten "Erschaffe" nur der Walker im Fokus seine Spur speichert
+    streaming: bool,
     average: AverageDistance,
+    plot_quantity: PlotQuantity,
+    walker_series: WalkerSeries,
+    log_log_plot: bool,
+    fit_enabled: bool,
+    fit_start: usize,
+    fit_end: usize,
     color1: Color32,
     color1_gradient: Color32,
     color2: Color32,
-    radio: RadioState,
-    strength_of_bias: f64,
+    model: WalkModel,
+    show_all_walkers: bool,
+    swarm_radius_plot_data: Vec<PlotPoint>,
+    central_view: CentralView,
+    ensemble: EnsembleView,
+    coverage: Coverage,
+    first_passage: FirstPassage,
+    observables: ObservablePanel,
+    arcsine: ArcsineView,
+    winding: WindingView,
+    runs: RepeatedRuns,
+    comparison: Comparison,
+    sweep: Sweep,
+    large_deviation: LargeDeviation,
+    wang_landau: WangLandau,
+    exact: ExactOverlay,
     mesh_change_tracker: MeshChangeTracker,
     perfomance_hint: PerformanceHint,
     light_mode: LightMode
@@ -66,17 +112,38 @@ impl Default for TemplateApp {
             current_time: 0.0,
             walker: None,
             canvas_size: 0.6,
-            old_mesh: None,
+            trail: TrailImage::default(),
             step_limit: 100000,
             seed: 2598,
             display_walker_id: 0,
             num_of_walkers: 10,
+            streaming: false,
             average: AverageDistance::default(),
+            plot_quantity: PlotQuantity::Distance,
+            walker_series: WalkerSeries::default(),
+            log_log_plot: false,
+            fit_enabled: false,
+            fit_start: 10,
+            fit_end: 100000,
             color1: Color32::from_rgb(80, 0, 161),
             color1_gradient: Color32::from_rgb(254, 42, 42),
             color2: Color32::DARK_RED,
-            radio: RadioState::NoBias,
-            strength_of_bias: 0.1,
+            model: WalkModel::default(),
+            show_all_walkers: false,
+            swarm_radius_plot_data: Vec::new(),
+            central_view: CentralView::Walker,
+            ensemble: EnsembleView::default(),
+            coverage: Coverage::default(),
+            first_passage: FirstPassage::default(),
+            observables: ObservablePanel::default(),
+            arcsine: ArcsineView::default(),
+            winding: WindingView::default(),
+            runs: RepeatedRuns::default(),
+            comparison: Comparison::default(),
+            sweep: Sweep::default(),
+            large_deviation: LargeDeviation::default(),
+            wang_landau: WangLandau::default(),
+            exact: ExactOverlay::default(),
             mesh_change_tracker: MeshChangeTracker::new(),
             perfomance_hint: PerformanceHint::PrioritizeOptics,
             light_mode: LightMode::Dark
@@ -91,21 +158,21 @@ impl TemplateApp {
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
         // Load previous app state (if any).
-        // Note that you must enable the `persistence` feature for this to work.
-        //if let Some(storage) = cc.storage {
-        //    return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
-        //}
+        // Only the step table and script of "Deine Funktion" are restored
+        let mut app = Self::default();
+        if let Some(storage) = cc.storage {
+            app.model.load(storage);
+        }
         cc.egui_ctx.set_visuals(Visuals::dark());
-        Default::default()
+        app
     }
 }
 
 impl eframe::App for TemplateApp {
     /// Called by the frame work to save state before shutdown.
-    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
-        //eframe::set_value(storage, eframe::APP_KEY, self);
-
-        // DO NOT SAVE
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // DO NOT SAVE the simulation itself, only the step table and script
+        self.model.save(storage);
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
@@ -117,17 +184,38 @@ impl eframe::App for TemplateApp {
             speed,
             current_time,
             canvas_size,
-            old_mesh,
+            trail,
             step_limit,
             seed,
             display_walker_id,
             num_of_walkers,
+            streaming,
             average,
+            plot_quantity,
+            walker_series,
+            log_log_plot,
+            fit_enabled,
+            fit_start,
+            fit_end,
             color1,
             color2,
             color1_gradient,
-            radio,
-            strength_of_bias,
+            model,
+            show_all_walkers,
+            swarm_radius_plot_data,
+            central_view,
+            ensemble,
+            coverage,
+            first_passage,
+            observables,
+            arcsine,
+            winding,
+            runs,
+            comparison,
+            sweep,
+            large_deviation,
+            wang_landau,
+            exact,
             mesh_change_tracker,
             perfomance_hint,
             light_mode
@@ -189,8 +277,9 @@ impl eframe::App for TemplateApp {
                         .changed(){
                         mesh_change_tracker.request_redraw();
                     }
+                    let (max_steps, max_walkers) = if *streaming { STREAMING_LIMITS } else { LIMITS };
                     ui.add(
-                        egui::Slider::new(step_limit, 1..=500000)
+                        egui::Slider::new(step_limit, 1..=max_steps)
                             .text("Schritt-limit")
                             .drag_value_speed(10.0)
                     ).on_hover_text("Haben die Walker dieses Schritt-limit erreicht oder überschritten werden sie pausiert");
@@ -198,40 +287,51 @@ impl eframe::App for TemplateApp {
                         .text("Seed")
                         .drag_value_speed(1.0)
                     );
-                    ui.add(egui::Slider::new(num_of_walkers, 1..=200)
+                    ui.add(egui::Slider::new(num_of_walkers, 1..=max_walkers)
+                        .logarithmic(*streaming)
                         .text("Anzahl an Walkern")
                         .drag_value_speed(0.5)
                     );
+                    if ui.checkbox(streaming, "Nur Statistik speichern")
+                        .on_hover_text("Streaming-Modus: Nur der Walker im Fokus speichert seine Spur, alle anderen nur ihre aktuelle Position. Damit gehen viel mehr Walker und Schritte, aber die besuchten Felder, Rückkehrzeiten und Messgrößen fehlen. Gilt ab dem nächsten 'Erschaffe'")
+                        .changed() && !*streaming
+                    {
+                        *step_limit = (*step_limit).min(LIMITS.0);
+                        *num_of_walkers = (*num_of_walkers).min(LIMITS.1);
+                    }
                     if ui.add(egui::Button::new(format!("Erschaffe {num_of_walkers} Walker")))
                         .on_hover_text("Startet die Simulation. Läuft schon eine Simulation so wird sie verworfen und mit den aktuellen Einstellungen wird eine neue gestartet.")
                         .clicked()
                     {
-                        let mut pcg = rand_pcg::Pcg64::seed_from_u64(*seed);
                         *current_time = 0.0;
                         let capacity = *step_limit as usize;
                         *walker = None; // Force rust to deallocate the old vectors before allocating new ones!
+                        *display_walker_id = (*display_walker_id).min(*num_of_walkers - 1);
                         *walker = Some(
-                            (0..*num_of_walkers)
-                                .map(
-                                    |_|
-                                    {
-                                        RandomWalker::with_capacity_and_rng(
-                                            Pcg64::from_rng(&mut pcg).unwrap(), 
-                                            capacity
-                                        )
-                                    }
-                                ).collect()
+                            if *streaming {
+                                create_streaming_walkers(*seed, *num_of_walkers, capacity, Some(*display_walker_id))
+                            } else {
+                                create_walkers(*seed, *num_of_walkers, capacity)
+                            }
                         );
                         mesh_change_tracker.request_redraw();
                     
                         *average = AverageDistance::default();
+                        swarm_radius_plot_data.clear();
+                        walker_series.reset();
+                        ensemble.reset();
+                        coverage.reset();
+                        first_passage.reset();
+                        observables.reset();
+                        runs.reset();
+                        comparison.reset(*seed);
+                        exact.reset();
                     }
                     ui.horizontal(
                         |ui|
                         {
                             ui.label("Farbe 1");
                             egui::color_picker::color_edit_button_srgba(ui, color1, egui::color_picker::Alpha::Opaque);
-                            mesh_change_tracker.request_redraw();
                         }
                     );
                     ui.horizontal(
@@ -239,7 +339,6 @@ impl eframe::App for TemplateApp {
                         {
                             ui.label("Farbe 2");
                             egui::color_picker::color_edit_button_srgba(ui, color1_gradient, egui::color_picker::Alpha::Opaque);
-                            mesh_change_tracker.request_redraw();
                         }
                     );
                     ui.horizontal(
@@ -247,24 +346,12 @@ impl eframe::App for TemplateApp {
                         {
                             ui.label("Farbe 3");
                             egui::color_picker::color_edit_button_srgba(ui, color2, egui::color_picker::Alpha::Opaque);
-                            mesh_change_tracker.request_redraw();
                         }
                     );
-                    ui.radio_value(radio, RadioState::NoBias, "Normaler Random Walk")
-                        .on_hover_text("Ist diese Option ausgewählt wird ein 'normaler' random walk ausgeführt. D.h. die Wahrscheinlichkeit für den Walker nach oben, unten, rechts oder links zu gehen ist identisch.");
-                    ui.radio_value(radio, RadioState::BiasedAwayFromOrigin, "Bias - weg vom Ursprung")
-                        .on_hover_text("Ist diese Option ausgewählt wird ein gebiaster random walk ausgeführt. Schritte die vom ursprung wegführen werden preferiert. Wie stark diese Präferenz ist hängt vom Bias ab.");
-                    ui.radio_value(radio, RadioState::BiasedTowardsOrigin, "Bias - zum Ursprung hin")
-                        .on_hover_text("Ist diese Option ausgewählt wird ein gebiaster random walk ausgeführt. Schritte die zum ursprung hinführen werden preferiert. Wie stark diese Präferenz ist hängt vom Bias ab.");
-                    ui.radio_value(radio, RadioState::YourFunction, "Deine Funktion")
-                        .on_hover_text("Du hast eine eigene Idee für einen Random Walker? Perfekt. Du kannst sie implementieren und hier auswählen");
-                    ui.add(
-                        egui::Slider::new(strength_of_bias, 0.0..=0.5)
-                        .logarithmic(true)
-                        .text("Bias")
-                        .smallest_positive(0.0005)
-                    );
-                
+                    model.ui(ui);
+
+                    ui.checkbox(show_all_walkers, "Alle Walker anzeigen")
+                        .on_hover_text("Zeigt zusätzlich die aktuellen Positionen aller anderen Walker an");
                     if let Some(walker) = walker{
                         if ui
                             .add(egui::Slider::new(display_walker_id, 0..=(walker.len()-1))
@@ -277,9 +364,9 @@ impl eframe::App for TemplateApp {
                     }
 
                     ui.radio_value(perfomance_hint, PerformanceHint::PrioritizeOptics, "Priorität: Optik")
-                        .on_hover_text("Dies wird das Bild bei jedem Schritt sanft umfärben und alle Schritte für das Diagramm verwenden");
+                        .on_hover_text("Dies wird alle Schritte für das Diagramm verwenden");
                     ui.radio_value(perfomance_hint, PerformanceHint::PrioritizePerformance, "Priorität: Leistung")
-                        .on_hover_text("Dies wird das Bild nur gelegentlich umfärben und nur jeden 100. Punkt im Diagramm verwenden");
+                        .on_hover_text("Dies wird nur jeden 100. Punkt im Diagramm verwenden. Das Bild des Walkers sieht in beiden Fällen gleich aus");
                 
                     let old = *current_time as u64;
                     *current_time += *speed;
@@ -303,210 +390,412 @@ impl eframe::App for TemplateApp {
             //));
 
             if let Some(walker_vec) = walker{
-                ui.with_layout(
-                    Layout::left_to_right(Align::TOP), 
-                    |ui|
-                    {
-                        let idx = *display_walker_id;
-                        ui.vertical(
+                ui.ctx().request_repaint();
+                if walker_vec[0].history.len() < *step_limit as usize {
+                    do_steps = model.step_and_measure(walker_vec, do_steps, average);
+                } else {
+                    do_steps = 0;
+                }
+                if model.radio == RadioState::Interacting && do_steps > 0 {
+                    swarm_radius_plot_data.push(
+                        PlotPoint{
+                            x: walker_vec[0].history.len() as f64,
+                            y: swarm_radius(walker_vec)
+                        }
+                    );
+                }
+                comparison.update(walker_vec);
+                ensemble.run_pending(model, *seed, *num_of_walkers);
+            }
+
+            // Diese Werkzeuge brauchen die Walker oben nicht und rechnen auch ohne sie weiter
+            runs.run_pending(model, *seed, *num_of_walkers);
+            sweep.run_pending();
+            large_deviation.run_pending();
+            wang_landau.run_pending();
+            if runs.is_busy() || sweep.is_busy() || large_deviation.is_busy() || wang_landau.is_busy() {
+                ui.ctx().request_repaint();
+            }
+
+            ui.horizontal(
+                |ui|
+                {
+                    let walker_view = ui.selectable_value(central_view, CentralView::Walker, "Walker");
+                    if walker_view.changed() {
+                        mesh_change_tracker.request_redraw();
+                    }
+                    ui.selectable_value(central_view, CentralView::Ensemble, "Verteilung")
+                        .on_hover_text("Wo befinden sich alle Walker gerade?");
+                    ui.selectable_value(central_view, CentralView::FirstPassage, "Rückkehrzeiten")
+                        .on_hover_text("Wann kommen die Walker zum ersten Mal zum Ursprung zurück oder erreichen ein Ziel?");
+                    ui.selectable_value(central_view, CentralView::Observables, "Messgrößen")
+                        .on_hover_text("Mittelwert und Quantile beliebiger Messgrößen über alle Walker");
+                    ui.selectable_value(central_view, CentralView::Arcsine, "Arkussinus")
+                        .on_hover_text("Wie lange sind die Walker rechts von der y-Achse und wann waren sie zuletzt auf ihr?");
+                    ui.selectable_value(central_view, CentralView::Winding, "Windungen")
+                        .on_hover_text("Wie oft haben sich die Walker um den Ursprung gedreht?");
+                    ui.selectable_value(central_view, CentralView::Runs, "Viele Läufe")
+                        .on_hover_text("Mittelt über viele unabhängige Läufe, weit über die 200 Walker hinaus");
+                    ui.selectable_value(central_view, CentralView::Comparison, "Vergleich")
+                        .on_hover_text("Vergleicht die Walker mit einem zweiten Modell, das vom gleichen seed aus startet");
+                    ui.selectable_value(central_view, CentralView::Sweep, "Parameter-Scan")
+                        .on_hover_text("Wie hängt der Abstand vom Bias oder einem anderen Parameter ab?");
+                    ui.selectable_value(central_view, CentralView::LargeDeviation, "Seltene Walks")
+                        .on_hover_text("Wie wahrscheinlich sind extrem weite oder kurze Walks? Auch Wahrscheinlichkeiten wie 10^-50 lassen sich so messen");
+                    ui.selectable_value(central_view, CentralView::WangLandau, "Wang-Landau")
+                        .on_hover_text("Schätzt die ganze Verteilung des Abstands, ohne dass man Temperaturen wählen muss");
+                }
+            );
+
+            match central_view{
+                CentralView::Runs => {
+                    runs.ui(ui, *num_of_walkers);
+                },
+                CentralView::Sweep => {
+                    sweep.ui(ui, model, *seed, *num_of_walkers);
+                },
+                CentralView::LargeDeviation => {
+                    large_deviation.ui(ui, model, *seed);
+                },
+                CentralView::WangLandau => {
+                    wang_landau.ui(ui, model, *seed);
+                },
+                _ => {}
+            }
+
+            if let Some(walker_vec) = walker{
+                let streaming_walkers = walker_vec.iter().any(|walker| !walker.history.keeps_trajectory());
+                let trajectory_of = walker_vec.iter().position(|walker| walker.history.keeps_trajectory());
+                match central_view{
+                    CentralView::Ensemble => {
+                        ensemble.ui(ui, walker_vec, model, *color1, *color1_gradient);
+                    },
+                    CentralView::FirstPassage | CentralView::Observables if streaming_walkers => {
+                        ui.label(NEEDS_TRAJECTORIES);
+                    },
+                    CentralView::FirstPassage => {
+                        first_passage.ui(ui, walker_vec);
+                    },
+                    CentralView::Observables => {
+                        observables.ui(ui, walker_vec);
+                    },
+                    CentralView::Arcsine => {
+                        arcsine.ui(ui, walker_vec);
+                    },
+                    CentralView::Winding => {
+                        winding.ui(ui, walker_vec);
+                    },
+                    CentralView::Comparison => {
+                        let style = CanvasStyle{
+                            zoom: *zoom,
+                            color1: *color1,
+                            color1_gradient: *color1_gradient,
+                            color2: *color2
+                        };
+                        comparison.ui(ui, walker_vec, average, model, *display_walker_id, style);
+                    },
+                    CentralView::Runs | CentralView::Sweep | CentralView::LargeDeviation | CentralView::WangLandau => {},
+                    CentralView::Walker => {
+                        ui.with_layout(
+                            Layout::left_to_right(Align::TOP), 
                             |ui|
                             {
-                                ui.label(format!("Walker {idx}"));
-
-                                Frame::canvas(ui.style())
-                                .fill(Color32::BLACK)
-                                .show(
-                                    ui, 
+                                let idx = *display_walker_id;
+                                ui.vertical(
                                     |ui|
                                     {
-                                        ui.ctx().request_repaint();
-                                        let min_len = ui.available_size().min_elem();
-                                        let desired_canvas = Vec2 { x: min_len, y: min_len } * Vec2{x: *canvas_size, y: *canvas_size};
+                                        ui.label(format!("Walker {idx}"));
+                                        if let Some(focus) = trajectory_of.filter(|focus| streaming_walkers && *focus != idx) {
+                                            ui.label(format!("Nur Walker {focus} speichert seine Spur"));
+                                        }
+
+                                        Frame::canvas(ui.style())
+                                        .fill(Color32::BLACK)
+                                        .show(
+                                            ui, 
+                                            |ui|
+                                            {
+                                                let min_len = ui.available_size().min_elem();
+                                                let desired_canvas = Vec2 { x: min_len, y: min_len } * Vec2{x: *canvas_size, y: *canvas_size};
         
-                                        let (response, painter) = ui
-                                            .allocate_painter(
-                                                desired_canvas, 
-                                                Sense::hover()
-                                            );
+                                                let (response, painter) = ui
+                                                    .allocate_painter(
+                                                        desired_canvas, 
+                                                        Sense::hover()
+                                                    );
         
-                                        let canvas_size = response.rect;
-
-                                        match radio{
-                                            RadioState::NoBias => {
-                                                walker_vec.par_iter_mut()
-                                                    .for_each(
-                                                        |walker|
-                                                        {
-                                                            if walker.history.len() < *step_limit as usize{
-                                                                for _ in 0..do_steps{
-                                                                    walker.random_step();
-                                                                }
-                                                            }
-                                                        }
+                                                let canvas_size = response.rect;
+
+                                                let pixels_per_point = ui.ctx().pixels_per_point();
+                                                let fits = trail.fits(&walker_vec[idx], canvas_size, pixels_per_point, *zoom);
+                                                if mesh_change_tracker.check_if_needs_redraw() || !fits {
+                                                    trail.redraw(
+                                                        &walker_vec[idx], 
+                                                        canvas_size, 
+                                                        pixels_per_point,
+                                                        *zoom
                                                     );
-                                            },
-                                            _ => {
-                                                let step_fun = match radio {
-                                                    RadioState::BiasedAwayFromOrigin => RandomWalker::random_step_biased_away,
-                                                    RadioState::BiasedTowardsOrigin => RandomWalker::random_step_biased_to_origin,
-                                                    RadioState::YourFunction => RandomWalker::your_step_function,
-                                                    _ => unreachable!()
-                                                };
-
-                                                walker_vec.par_iter_mut()
-                                                    .for_each(
-                                                        |walker|
-                                                        {
-                                                            if walker.history.len() < *step_limit as usize{
-                                                                for _ in 0..do_steps{
-                                                                    step_fun(walker, *strength_of_bias);
-                                                                }
-                                                            }
-                                                        }
+                                                    mesh_change_tracker.redraw_finished();
+                                                } else if trail.drawn_steps() != walker_vec[idx].history.len() {
+                                                    trail.update(&walker_vec[idx]);
+                                                }
+                                                trail.tint(*color1, *color1_gradient);
+                                                trail.paint(ui.ctx(), &painter, canvas_size, &walker_vec[idx], *color2);
+                                                if *show_all_walkers {
+                                                    painter.add(
+                                                        crate::animation::walker_positions_mesh(
+                                                            walker_vec,
+                                                            canvas_size,
+                                                            *zoom,
+                                                            Color32::WHITE
+                                                        )
                                                     );
-                                            }
-                                        };
-
-                                        
-                                        if do_steps > 0 && average.average_distance_plot_data.len() < *step_limit as usize {
-                                            average.update_on_step_of_walkers(do_steps as usize, walker_vec);
-                                            if perfomance_hint == &PerformanceHint::PrioritizeOptics{
-                                                mesh_change_tracker.request_redraw();   
-                                            }
-                                        }
-                                        
-                                        let mesh = if mesh_change_tracker.check_if_needs_redraw(*speed, *perfomance_hint) || old_mesh.is_none() {
-                                            let mesh = crate::animation::calc_mesh(
-                                                &walker_vec[idx], 
-                                                canvas_size, 
-                                                *zoom,
-                                                *color1,
-                                                *color1_gradient,
-                                                *color2
-                                            );
-                                            let total_steps = walker_vec[idx].history.len();
-                                            mesh_change_tracker.redraw_finished(total_steps);
-                                            *old_mesh = Some(mesh.clone());
-                                            mesh
-                                        } else {
-                                            let saved_mesh = old_mesh.as_mut().unwrap();
-                                            let old_steps = mesh_change_tracker.get_current_step();
-                                            let new_steps = walker_vec[idx].history.len();
-                                            if old_steps != new_steps{
-                                                crate::animation::update_mesh(
-                                                    saved_mesh, 
-                                                    old_steps, 
-                                                    &walker_vec[idx], 
-                                                    canvas_size, 
-                                                    *zoom, 
-                                                    *color2
-                                                );
-                                                mesh_change_tracker.new_steps(new_steps);
-                                                if new_steps >= *step_limit as usize {
-                                                    mesh_change_tracker.request_redraw();
                                                 }
                                             }
-                                            
-                                            saved_mesh.clone()
-                                        };
-        
-                                        painter.add(mesh);
+                                        );
                                     }
                                 );
-                            }
-                        );
 
-                        let max_reached = walker_vec[idx].history.len();
-
-                        let step_size = max_reached as f64 / 1000.0;
-                        let factor = std::f64::consts::PI.sqrt() / 2.0;
-
-                        let analytical: Vec<_> = (0..1000_u32)
-                            .map(
-                                |i|
-                                {
-                                    let x = (i as f64) * step_size;
-                                    let y = x.sqrt() * factor;
-                                    [x,y]
-                                }
-                            ).collect();
-                        
-
-                        
-                        
-                        let distance: Vec<PlotPoint> = match *perfomance_hint{
-                            PerformanceHint::PrioritizeOptics => {
-                                walker_vec[idx]
-                                    .history
-                                    .distance_from_origin
-                                    .par_iter()
-                                    .enumerate()
-                                    .map(|(index, dist)| PlotPoint { x: index as f64, y: *dist as f64})
-                                    .collect()
-                            },
-                            _ => {
-                                walker_vec[idx]
-                                    .history
-                                    .distance_from_origin
-                                    .par_iter()
-                                    .enumerate()
-                                    .step_by(100)
-                                    .map(|(index, dist)| PlotPoint { x: index as f64, y: *dist as f64 })
-                                    .collect()
-                            }
-                        };
+                                let max_reached = walker_vec[idx].history.len();
 
-                        ui.vertical_centered(
-                            |ui|
-                            {
-                                ui.label("Abstand vom Urspurng");
-
-                                let hight = ui.available_height();
-                                Plot::new("plot_average_etc")
-                                .include_x(0.0)
-                                .legend(Legend::default())
-                                .height(hight - 25.0)
-                                .show(
-                                    ui, 
-                                    |plot_ui|
+                                let step_size = max_reached as f64 / 1000.0;
+                                let curve = |f: &dyn Fn(f64) -> f64| -> Vec<PlotPoint> {
+                                    (0..1000_u32)
+                                        .map(
+                                            |i|
+                                            {
+                                                let x = (i as f64) * step_size;
+                                                PlotPoint { x, y: f(x) }
+                                            }
+                                        ).collect()
+                                };
+
+                                let quantity = *plot_quantity;
+                                let squared = quantity == PlotQuantity::SquaredDistance;
+                                let sites = quantity == PlotQuantity::DistinctSites;
+                                let average_stats = match quantity{
+                                    PlotQuantity::Distance => &average.distance,
+                                    PlotQuantity::SquaredDistance => &average.squared_distance,
+                                    PlotQuantity::DistinctSites => &average.distinct_sites,
+                                    PlotQuantity::RadiusOfGyration => &average.radius_of_gyration,
+                                    PlotQuantity::HullArea => &average.hull_area
+                                };
+
+                                let steps_per_entry = average.steps_per_entry();
+                                let last_average = average_stats.len().checked_sub(1)
+                                    .map(|i| ((i * steps_per_entry) as f64, average_stats[i].mean()));
+                                let analytical = predictions(model, quantity, last_average);
+
+                                let stride = match *perfomance_hint{
+                                    PerformanceHint::PrioritizeOptics => 1,
+                                    _ => 100
+                                };
+                                let history = &walker_vec[idx].history;
+                                let distance: Vec<PlotPoint> = walker_series.update(
+                                    history,
+                                    (idx, quantity, stride),
+                                    |record| match quantity{
+                                        PlotQuantity::Distance => record.distance_from_origin as f64,
+                                        PlotQuantity::SquaredDistance => {
+                                            let pos = &record.position;
+                                            (pos.x as f64).powi(2) + (pos.y as f64).powi(2)
+                                        },
+                                        PlotQuantity::DistinctSites => record.distinct_sites as f64,
+                                        PlotQuantity::RadiusOfGyration => record.radius_of_gyration as f64,
+                                        PlotQuantity::HullArea => record.hull_area as f64
+                                    }
+                                ).to_vec();
+                                // Höchstens 500 Vierecke für das Fehlerband
+                                let band_stride = (average_stats.len() / 500).max(1);
+                                let error_band = AverageDistance::error_band(average_stats, steps_per_entry, band_stride);
+
+                                // In Schritten, ein Eintrag der Statistik kann zu mehreren gehören
+                                let fit_window = {
+                                    let end = (*fit_end).min(average_stats.len() * steps_per_entry);
+                                    let start = (*fit_start).max(1).min(end);
+                                    start..end
+                                };
+                                let fit = if *fit_enabled {
+                                    let first = (fit_window.start + steps_per_entry - 1) / steps_per_entry;
+                                    let end = (fit_window.end + steps_per_entry - 1) / steps_per_entry;
+                                    let points: Vec<_> = average_stats[first.min(end)..end]
+                                        .iter()
+                                        .zip(first..)
+                                        .map(|(w, i)| PlotPoint { x: (i * steps_per_entry) as f64, y: w.mean() })
+                                        .collect();
+                                    fit_power_law(&points)
+                                } else {
+                                    None
+                                };
+
+                                ui.vertical_centered(
+                                    |ui|
                                     {
-                                        let line = Line::new(PlotPoints::Owned(distance))
-                                            .name(format!("walker {idx}"))
-                                            .color(*color2);
-                                        plot_ui.line(line);
-                                        
-
-                                        let average_distance = match *perfomance_hint
-                                        {
-                                            PerformanceHint::PrioritizeOptics => {
-                                                average
-                                                    .cloned_average()
-                                            },
-                                            _ => {
-                                                average.get_approximation()
+                                        ui.horizontal(
+                                            |ui|
+                                            {
+                                                ui.label("Abstand vom Urspurng");
+                                                ui.selectable_value(plot_quantity, PlotQuantity::Distance, "Abstand");
+                                                ui.selectable_value(plot_quantity, PlotQuantity::SquaredDistance, "Abstandsquadrat (MSD)")
+                                                    .on_hover_text("Das mittlere Abstandsquadrat (mean squared displacement). Für den normalen Random Walk ist es genau t");
+                                                ui.selectable_value(plot_quantity, PlotQuantity::DistinctSites, "Besuchte Felder S(t)")
+                                                    .on_hover_text("Auf wie vielen verschiedenen Feldern der Walker schon war. Für den normalen Random Walk wächst das wie π·t / ln(t)");
+                                                ui.selectable_value(plot_quantity, PlotQuantity::RadiusOfGyration, "Gyrationsradius")
+                                                    .on_hover_text("Wie weit die bisherigen Positionen im Mittel von ihrem Schwerpunkt entfernt sind. Ein Maß für die Größe der ganzen Spur");
+                                                ui.selectable_value(plot_quantity, PlotQuantity::HullArea, "Konvexe Hülle")
+                                                    .on_hover_text("Die Fläche des kleinsten konvexen Vielecks, das alle besuchten Felder enthält");
+                                                ui.checkbox(log_log_plot, "log-log")
+                                                    .on_hover_text("Beide Achsen logarithmisch. Ein Potenzgesetz a·t^b wird dann zu einer Geraden mit Steigung b");
+                                                ui.checkbox(fit_enabled, "Fit a·t^b")
+                                                    .on_hover_text("Passt ein Potenzgesetz an den Durchschnitt im gewählten Schritt-Fenster an");
+                                                if *fit_enabled {
+                                                    ui.label("von");
+                                                    ui.add(egui::DragValue::new(fit_start).clamp_range(1..=*fit_end));
+                                                    ui.label("bis");
+                                                    ui.add(egui::DragValue::new(fit_end).clamp_range(*fit_start..=usize::MAX));
+                                                }
+                                                if matches!(quantity, PlotQuantity::Distance | PlotQuantity::SquaredDistance) {
+                                                    exact.ui(ui, model);
+                                                }
+                                            }
+                                        );
+                                        if sites && streaming_walkers {
+                                            ui.label(NEEDS_TRAJECTORIES);
+                                        } else if sites {
+                                            coverage.update(walker_vec, model.box_half_width);
+                                            coverage.ui(ui, idx, model.box_enabled);
+                                        }
+                                        let log = *log_log_plot;
+                                        let transform = |points: Vec<PlotPoint>| {
+                                            if log {
+                                                log_log(points)
+                                            } else {
+                                                points
                                             }
                                         };
 
-                                        let line = Line::new(PlotPoints::Owned(average_distance)).name("average");
-                                        plot_ui.line(line);
-                                        let analytical_line = Line::new(analytical).name("analytical Results");
-                                        plot_ui.line(analytical_line);
+                                        let hight = ui.available_height();
+                                        // Eigene id, damit die Achsen beim Umschalten neu angepasst werden
+                                        let plot_id = if log { "plot_average_etc_log" } else { "plot_average_etc" };
+                                        let mut plot = Plot::new(plot_id)
+                                            .include_x(0.0)
+                                            .legend(Legend::default())
+                                            .height(hight - 25.0);
+                                        if log {
+                                            plot = plot
+                                                .x_axis_formatter(|v, _| log_axis_label(v))
+                                                .y_axis_formatter(|v, _| log_axis_label(v))
+                                                .label_formatter(
+                                                    |name, p| format!("{name}\nt = {}\ny = {}", log_axis_label(p.x), log_axis_label(p.y))
+                                                );
+                                        }
+                                        plot.show(
+                                            ui, 
+                                            |plot_ui|
+                                            {
+                                                let line = Line::new(PlotPoints::Owned(transform(distance)))
+                                                    .name(format!("walker {idx}"))
+                                                    .color(*color2);
+                                                plot_ui.line(line);
                                         
-                                        //let y = plot_ui.plot_bounds().max()[1];
-                                        //let x = plot_ui.plot_bounds().max()[0];
-                                        //
-                                        //let text = egui::plot::Text::new(PlotPoint { x: x / 20.0, y: y / 2.0 }, "d")
-                                        //    .anchor(Align2::LEFT_CENTER);
-                                        //plot_ui.text(text);
+
+                                                let average_distance = if quantity != PlotQuantity::Distance {
+                                                    AverageDistance::means(average_stats, steps_per_entry, (stride / steps_per_entry).max(1))
+                                                } else {
+                                                    match *perfomance_hint
+                                                    {
+                                                        PerformanceHint::PrioritizeOptics => {
+                                                            average
+                                                                .cloned_average()
+                                                        },
+                                                        _ => {
+                                                            average.get_approximation()
+                                                        }
+                                                    }
+                                                };
+
+                                                let average_color = Color32::from_rgb(0, 150, 255);
+                                                for quad in error_band {
+                                                    let corners: Vec<_> = quad.iter()
+                                                        .map(|[x, y]| PlotPoint { x: *x, y: *y })
+                                                        .collect();
+                                                    let corners = transform(corners);
+                                                    if corners.len() < 4 {
+                                                        continue;
+                                                    }
+                                                    let polygon = Polygon::new(PlotPoints::Owned(corners))
+                                                        .name("± Standardfehler")
+                                                        .color(average_color)
+                                                        .fill_alpha(0.3)
+                                                        .width(0.0);
+                                                    plot_ui.polygon(polygon);
+                                                }
+                                                let line = Line::new(PlotPoints::Owned(transform(average_distance)))
+                                                    .name("average")
+                                                    .color(average_color);
+                                                plot_ui.line(line);
+                                                if model.radio == RadioState::Interacting && quantity == PlotQuantity::Distance {
+                                                    let line = Line::new(PlotPoints::Owned(transform(swarm_radius_plot_data.clone())))
+                                                        .name("Schwarmradius");
+                                                    plot_ui.line(line);
+                                                }
+                                                for prediction in analytical.curves {
+                                                    let mut points = curve(&prediction.f);
+                                                    points.retain(|p| p.x >= prediction.from);
+                                                    let analytical_line = Line::new(PlotPoints::Owned(transform(points))).name(prediction.name);
+                                                    plot_ui.line(analytical_line);
+                                                }
+                                                if let Some(note) = analytical.note {
+                                                    // Ohne Punkte, damit der Hinweis nur in der Legende steht
+                                                    plot_ui.line(Line::new(PlotPoints::Owned(Vec::new())).name(note));
+                                                }
+                                                if matches!(quantity, PlotQuantity::Distance | PlotQuantity::SquaredDistance) {
+                                                    if let Some(points) = exact.curve(squared) {
+                                                        let line = Line::new(PlotPoints::Owned(transform(points)))
+                                                            .name("exakt")
+                                                            .width(2.0);
+                                                        plot_ui.line(line);
+                                                    }
+                                                }
+                                                if let Some(fit) = fit {
+                                                    let start = fit_window.start as f64;
+                                                    let width = (fit_window.end - fit_window.start) as f64;
+                                                    let points = (0..=200)
+                                                        .map(
+                                                            |i|
+                                                            {
+                                                                let x = start + width * i as f64 / 200.0;
+                                                                PlotPoint { x, y: fit.eval(x) }
+                                                            }
+                                                        ).collect();
+                                                    let line = Line::new(PlotPoints::Owned(transform(points)))
+                                                        .name(format!("Fit: {:.3}·t^{:.3}", fit.prefactor, fit.exponent))
+                                                        .width(2.0)
+                                                        .style(LineStyle::dashed_loose());
+                                                    plot_ui.line(line);
+                                                    for x in [fit_window.start, fit_window.end] {
+                                                        let x = if log { (x.max(1) as f64).log10() } else { x as f64 };
+                                                        plot_ui.vline(VLine::new(x).name("Fit-Fenster").style(LineStyle::dotted_dense()));
+                                                    }
+                                                }
+                                        
+                                                //let y = plot_ui.plot_bounds().max()[1];
+                                                //let x = plot_ui.plot_bounds().max()[0];
+                                                //
+                                                //let text = egui::plot::Text::new(PlotPoint { x: x / 20.0, y: y / 2.0 }, "d")
+                                                //    .anchor(Align2::LEFT_CENTER);
+                                                //plot_ui.text(text);
+                                            }
+                                        );
+                                        ui.label("Steps");
                                     }
                                 );
-                                ui.label("Steps");
+
                             }
-                        );
 
+                        );
                     }
-
-                );
+                }
             }
         });
 
@@ -520,3 +809,43 @@ impl eframe::App for TemplateApp {
         }
     }
 }
+
+/// Eine Messgröße eines Walkers für jeden `stride`-ten Schritt als Punkte für das Diagramm.
+///
+/// Die Punkte werden von Bild zu Bild aufgehoben, aus der History werden nur die
+/// neuen Schritte gelesen
+#[derive(Debug, Default)]
+struct WalkerSeries{
+    /// Walker, Messgröße und stride, zu denen `points` gehört
+    key: Option<(usize, PlotQuantity, usize)>,
+    points: Vec<PlotPoint>,
+    /// Der nächste Eintrag, der noch fehlt
+    next: usize
+}
+
+impl WalkerSeries{
+    /// Vergisst alle Punkte, z.B. wenn neue Walker erschaffen wurden
+    fn reset(&mut self)
+    {
+        *self = Self::default();
+    }
+
+    fn update(
+        &mut self,
+        history: &History,
+        key: (usize, PlotQuantity, usize),
+        value: impl Fn(&StepRecord) -> f64
+    ) -> &[PlotPoint]
+    {
+        if self.key != Some(key) {
+            self.reset();
+            self.key = Some(key);
+        }
+        let stride = key.2;
+        for record in history.records(self.next).step_by(stride){
+            self.points.push(PlotPoint { x: record.step as f64, y: value(&record) });
+            self.next = record.step + stride;
+        }
+        &self.points
+    }
+}
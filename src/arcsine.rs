@@ -0,0 +1,160 @@
+use egui::plot::*;
+
+use crate::ensemble::{curve, histogram};
+use crate::random_walker::{Position, RandomWalker};
+
+/// Wie viele Balken die Histogramme haben
+const HISTOGRAM_BINS: usize = 20;
+
+/// Zähler für die Arkussinus-Gesetze, die bei jedem Schritt mitlaufen:
+/// Wie oft war der Walker rechts von der y-Achse (x > 0)
+/// und wann war er zuletzt auf ihr (x = 0)?
+///
+/// Steht der Walker auf der y-Achse, zählt er zu der Seite, von der er gekommen ist.
+/// So werden die Schritte auf der Achse fair auf beide Seiten verteilt.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::arcsine::HalfPlane;
+/// use girls_day::random_walker::Position;
+/// let mut half_plane = HalfPlane::default();
+/// for x in [0, 1, 2, 1, 0, -1, 0, 0, 1] {
+///     half_plane.add(&Position { x, y: 0 });
+/// }
+/// assert_eq!(half_plane.steps(), 9);
+/// // Der Start zählt zu keiner Seite, die Nullen bei 4, 6 und 7 zur Seite davor
+/// assert_eq!(half_plane.time_positive(), 5);
+/// assert_eq!(half_plane.last_zero(), 7);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HalfPlane{
+    steps: u32,
+    time_positive: u32,
+    last_zero: u32,
+    /// Auf welcher Seite der Walker zuletzt war, 0 solange er die Achse nie verlassen hat
+    last_sign: i32
+}
+
+impl HalfPlane{
+    pub fn add(&mut self, pos: &Position)
+    {
+        if pos.x == 0 {
+            self.last_zero = self.steps;
+        } else {
+            self.last_sign = pos.x.signum();
+        }
+        if self.last_sign > 0 {
+            self.time_positive += 1;
+        }
+        self.steps += 1;
+    }
+
+    pub fn steps(&self) -> u32
+    {
+        self.steps
+    }
+
+    /// Wie viele Positionen rechts von der y-Achse lagen
+    pub fn time_positive(&self) -> u32
+    {
+        self.time_positive
+    }
+
+    /// Der letzte Schritt, bei dem der Walker auf der y-Achse stand
+    pub fn last_zero(&self) -> u32
+    {
+        self.last_zero
+    }
+
+    /// Anteil der Zeit mit x > 0
+    pub fn fraction_positive(&self) -> f64
+    {
+        self.time_positive as f64 / self.steps.max(1) as f64
+    }
+
+    /// Der letzte Besuch der y-Achse als Anteil der bisherigen Zeit
+    pub fn fraction_last_zero(&self) -> f64
+    {
+        self.last_zero as f64 / self.steps.max(1) as f64
+    }
+}
+
+/// Dichte der Arkussinus-Verteilung 1 / (π·√(p·(1-p))).
+/// Für lange Walks sind beide Anteile so verteilt
+pub fn arcsine_density(p: f64) -> f64
+{
+    1.0 / (std::f64::consts::PI * (p * (1.0 - p)).sqrt())
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ArcsineQuantity{
+    TimePositive,
+    LastZero
+}
+
+/// Histogramme der beiden Anteile über alle Walker zusammen mit der Arkussinus-Dichte
+#[derive(Debug, Clone)]
+pub struct ArcsineView{
+    pub quantity: ArcsineQuantity
+}
+
+impl Default for ArcsineView{
+    fn default() -> Self {
+        Self { quantity: ArcsineQuantity::TimePositive }
+    }
+}
+
+impl ArcsineView{
+    pub fn ui(&mut self, ui: &mut egui::Ui, walkers: &[RandomWalker])
+    {
+        ui.horizontal(
+            |ui|
+            {
+                ui.selectable_value(&mut self.quantity, ArcsineQuantity::TimePositive, "Zeit mit x > 0")
+                    .on_hover_text("Welchen Anteil der Zeit der Walker rechts von der y-Achse verbracht hat");
+                ui.selectable_value(&mut self.quantity, ArcsineQuantity::LastZero, "Letzter Besuch von x = 0")
+                    .on_hover_text("Wann der Walker zuletzt auf der y-Achse war, als Anteil der bisherigen Zeit");
+            }
+        );
+        let steps = walkers[0].history.half_plane.steps();
+        ui.label(
+            format!("{} Walker nach {steps} Schritten", walkers.len())
+        ).on_hover_text("Überraschend: Am wahrscheinlichsten ist nicht die Hälfte, sondern fast immer oder fast nie auf einer Seite");
+        if steps < 2 {
+            ui.label("Noch keine Daten");
+            return;
+        }
+
+        let values: Vec<f64> = walkers.iter()
+            .map(
+                |walker|
+                {
+                    let half_plane = &walker.history.half_plane;
+                    match self.quantity{
+                        ArcsineQuantity::TimePositive => half_plane.fraction_positive(),
+                        ArcsineQuantity::LastZero => half_plane.fraction_last_zero()
+                    }
+                }
+            ).collect();
+        let bin_width = 1.0 / HISTOGRAM_BINS as f64;
+        // Ein Balken mehr, damit auch der Anteil 1 einen Platz hat
+        let bars = histogram(&values, 0.0, bin_width, HISTOGRAM_BINS + 1);
+        let density = curve(0.005, 0.995, arcsine_density);
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+
+        Plot::new("arcsine_histogram")
+            .legend(Legend::default())
+            .include_x(0.0)
+            .include_x(1.0)
+            .include_y(0.0)
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    plot_ui.bar_chart(BarChart::new(bars).name("Walker"));
+                    plot_ui.line(Line::new(PlotPoints::Owned(density)).name("Arkussinus-Dichte 1 / (π·√(p·(1-p)))"));
+                    plot_ui.vline(VLine::new(mean).name("Mittelwert").style(LineStyle::dashed_loose()));
+                }
+            );
+    }
+}
@@ -0,0 +1,257 @@
+use egui::{
+    containers::Frame,
+    plot::*,
+    Color32,
+    Sense,
+    Vec2
+};
+
+use crate::animation::TrailImage;
+use crate::ensemble::{create_walkers, create_streaming_walkers, steps_per_update};
+use crate::random_walker::{AverageDistance, RandomWalker, Welford};
+use crate::walk_model::{RadioState, WalkModel};
+
+const COLOR_A: Color32 = Color32::from_rgb(0, 150, 255);
+const COLOR_B: Color32 = Color32::from_rgb(255, 170, 0);
+
+/// Was die Walker zeichnen
+#[derive(Debug, Clone, Copy)]
+pub struct CanvasStyle{
+    pub zoom: f32,
+    pub color1: Color32,
+    pub color1_gradient: Color32,
+    pub color2: Color32
+}
+
+/// Vergleich zweier Modelle: Ein zweites Ensemble läuft mit einer anderen
+/// Schrittregel (Modell B), aber mit dem gleichen seed wie die eigentlichen Walker (Modell A).
+///
+/// Modell B läuft immer so viele Schritte wie Modell A, auch wenn der Vergleich
+/// gerade nicht angezeigt wird - aber erst, nachdem er einmal geöffnet wurde.
+/// Wird es neu gestartet, holt es die fehlenden Schritte nach und nach auf.
+#[derive(Debug, Clone)]
+pub struct Comparison{
+    pub model: WalkModel,
+    seed: u64,
+    walkers: Option<Vec<RandomWalker>>,
+    pub average: AverageDistance,
+    pub squared: bool,
+    invalid: bool,
+    /// Ob der Vergleich schon einmal angezeigt wurde. Vorher läuft Modell B nicht mit
+    opened: bool,
+    /// Die Spuren von Walker `shown` in Modell A und B
+    trails: [TrailImage; 2],
+    shown: Option<usize>
+}
+
+impl Default for Comparison{
+    fn default() -> Self {
+        Self{
+            model: WalkModel{
+                radio: RadioState::BiasedTowardsOrigin,
+                ..WalkModel::default()
+            },
+            seed: 0,
+            walkers: None,
+            average: AverageDistance::default(),
+            squared: false,
+            invalid: false,
+            opened: false,
+            trails: Default::default(),
+            shown: None
+        }
+    }
+}
+
+impl Comparison{
+    /// Wirft die Walker von Modell B weg. Beim nächsten Update werden sie
+    /// mit `seed` neu erschaffen
+    pub fn reset(&mut self, seed: u64)
+    {
+        self.seed = seed;
+        self.walkers = None;
+        self.average = AverageDistance::default();
+        self.invalid = false;
+        self.shown = None;
+    }
+
+    /// Lässt Modell B so viele Schritte machen, wie Modell A schon gemacht hat,
+    /// höchstens aber [`crate::ensemble::MAX_WALKER_STEPS_PER_UPDATE`] Schritte aller Walker zusammen
+    pub fn update(&mut self, walkers_a: &[RandomWalker])
+    {
+        if !self.opened {
+            return;
+        }
+        let target = walkers_a[0].history.len();
+        let (seed, n) = (self.seed, walkers_a.len());
+        let capacity = walkers_a[0].history.capacity();
+        // Speichert bei A nur ein Walker seine Spur, dann auch bei B
+        let streaming = walkers_a.iter().any(|walker| !walker.history.keeps_trajectory());
+        let focus = walkers_a.iter().position(|walker| walker.history.keeps_trajectory());
+        let walkers = self.walkers.get_or_insert_with(
+            ||
+            {
+                if streaming {
+                    create_streaming_walkers(seed, n, capacity, focus)
+                } else {
+                    create_walkers(seed, n, capacity)
+                }
+            }
+        );
+        let done = walkers[0].history.len();
+        if done >= target {
+            return;
+        }
+        let steps = steps_per_update(n, target - done);
+        if self.model.step_and_measure(walkers, steps as u64, &mut self.average) == 0 {
+            // Die Schrittregel von Modell B ist gerade ungültig
+            self.invalid = true;
+            return;
+        }
+        self.invalid = false;
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        walkers_a: &[RandomWalker],
+        average_a: &AverageDistance,
+        model_a: &WalkModel,
+        idx: usize,
+        style: CanvasStyle
+    )
+    {
+        ui.horizontal(
+            |ui|
+            {
+                ui.push_id(
+                    "model_b",
+                    |ui|
+                    {
+                        ui.collapsing(
+                            format!("Modell B: {}", self.model.radio.label()),
+                            |ui| self.model.ui(ui)
+                        );
+                    }
+                );
+                if ui.button("Modell B neu starten")
+                    .on_hover_text("Nach einer Änderung an Modell B: Startet die Walker von Modell B mit dem gleichen seed neu")
+                    .clicked()
+                {
+                    self.reset(self.seed);
+                }
+                ui.checkbox(&mut self.squared, "Abstandsquadrat (MSD)");
+            }
+        );
+        if !self.opened {
+            self.opened = true;
+            self.update(walkers_a);
+        }
+        if self.invalid {
+            ui.colored_label(ui.visuals().error_fg_color, "Die Schrittregel von Modell B ist ungültig");
+        }
+        let Some(walkers_b) = &self.walkers else {
+            return;
+        };
+        let behind = walkers_a[0].history.len() - walkers_b[0].history.len();
+        if behind > 0 {
+            ui.horizontal(
+                |ui|
+                {
+                    ui.spinner();
+                    ui.label(format!("Modell B holt noch {behind} Schritte auf"));
+                }
+            );
+        }
+
+        let idx = idx.min(walkers_b.len() - 1);
+        if self.shown != Some(idx) {
+            self.trails = Default::default();
+            self.shown = Some(idx);
+        }
+        let trails = &mut self.trails;
+        ui.horizontal_top(
+            |ui|
+            {
+                let size = (ui.available_width() / 2.0 - 10.0).min(ui.available_height() / 2.0).max(50.0);
+                let canvases = [
+                    (format!("Modell A: {}", model_a.radio.label()), walkers_a, COLOR_A),
+                    (format!("Modell B: {}", self.model.radio.label()), walkers_b.as_slice(), COLOR_B)
+                ];
+                for ((name, walkers, color), trail) in canvases.into_iter().zip(trails.iter_mut()){
+                    ui.vertical(
+                        |ui|
+                        {
+                            ui.colored_label(color, format!("{name}, Walker {idx}"));
+                            Frame::canvas(ui.style())
+                                .fill(Color32::BLACK)
+                                .show(
+                                    ui,
+                                    |ui|
+                                    {
+                                        let (response, painter) = ui.allocate_painter(Vec2::splat(size), Sense::hover());
+                                        let walker = &walkers[idx];
+                                        let pixels_per_point = ui.ctx().pixels_per_point();
+                                        if trail.fits(walker, response.rect, pixels_per_point, style.zoom) {
+                                            trail.update(walker);
+                                        } else {
+                                            trail.redraw(walker, response.rect, pixels_per_point, style.zoom);
+                                        }
+                                        trail.tint(style.color1, style.color1_gradient);
+                                        trail.paint(ui.ctx(), &painter, response.rect, walker, style.color2);
+                                    }
+                                );
+                        }
+                    );
+                }
+            }
+        );
+
+        let curves = [
+            ("Modell A", average_a, COLOR_A),
+            ("Modell B", &self.average, COLOR_B)
+        ];
+        Plot::new("comparison_plot")
+            .legend(Legend::default())
+            .include_x(0.0)
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    for (name, average, color) in curves{
+                        let stats = plotted_stats(average, self.squared);
+                        let steps_per_entry = average.steps_per_entry();
+                        let band_stride = (stats.len() / 500).max(1);
+                        for quad in AverageDistance::error_band(stats, steps_per_entry, band_stride){
+                            let corners = quad.iter()
+                                .map(|[x, y]| PlotPoint { x: *x, y: *y })
+                                .collect();
+                            plot_ui.polygon(
+                                Polygon::new(PlotPoints::Owned(corners))
+                                    .name(name)
+                                    .color(color)
+                                    .fill_alpha(0.3)
+                                    .width(0.0)
+                            );
+                        }
+                        let stride = (stats.len() / 2000).max(1);
+                        plot_ui.line(
+                            Line::new(PlotPoints::Owned(AverageDistance::means(stats, steps_per_entry, stride)))
+                                .name(name)
+                                .color(color)
+                        );
+                    }
+                }
+            );
+    }
+}
+
+/// Abstand oder Abstandsquadrat
+fn plotted_stats(average: &AverageDistance, squared: bool) -> &[Welford]
+{
+    if squared {
+        &average.squared_distance
+    } else {
+        &average.distance
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use rayon::prelude::*;
+
+use crate::random_walker::{History, Position, RandomWalker};
+
+fn inside(pos: &Position, half_width: i32) -> bool
+{
+    pos.x.abs() <= half_width && pos.y.abs() <= half_width
+}
+
+/// Wie viele Felder im Kasten von -half_width bis half_width liegen
+pub fn box_area(half_width: i32) -> usize
+{
+    let side = 2 * half_width as usize + 1;
+    side * side
+}
+
+/// Wie viele verschiedene Felder im Kasten schon besucht wurden.
+///
+/// Je nachdem was kleiner ist, werden entweder die besuchten Felder
+/// oder die Felder des Kastens durchsucht
+pub fn sites_in_box(history: &History, half_width: i32) -> usize
+{
+    if history.visited.len() < box_area(half_width) {
+        history.visited
+            .iter()
+            .filter(|pos| inside(pos, half_width))
+            .count()
+    } else {
+        (-half_width..=half_width)
+            .flat_map(|x| (-half_width..=half_width).map(move |y| Position { x, y }))
+            .filter(|pos| history.visited.contains(pos))
+            .count()
+    }
+}
+
+/// Der erste Schritt, bei dem alle Felder im Kasten besucht waren (cover time).
+/// None, wenn das noch nicht passiert ist
+///
+/// Beispiel:
+/// ```
+/// use girls_day::random_walker::RandomWalker;
+/// use girls_day::coverage::{cover_time, sites_in_box};
+/// let mut walker = RandomWalker::new(7);
+/// while cover_time(&walker.history, 1).is_none() {
+///     walker.random_step();
+///     walker.keep_inside_box(1);
+/// }
+/// assert_eq!(sites_in_box(&walker.history, 1), 9);
+/// assert_eq!(walker.history.visited.len(), 9);
+/// ```
+pub fn cover_time(history: &History, half_width: i32) -> Option<usize>
+{
+    let area = box_area(half_width);
+    let mut seen = HashSet::new();
+    history.positions(0)
+        .position(|pos| inside(&pos, half_width) && seen.insert(pos) && seen.len() == area)
+}
+
+/// Welcher Anteil des Kastens von jedem Walker schon besucht wurde
+/// und wann der Kasten vollständig besucht war
+#[derive(Debug, Clone, Default)]
+pub struct Coverage{
+    half_width: i32,
+    fractions: Vec<f64>,
+    cover_times: Vec<Option<usize>>
+}
+
+impl Coverage{
+    pub fn reset(&mut self)
+    {
+        *self = Self::default();
+    }
+
+    pub fn update(&mut self, walkers: &[RandomWalker], half_width: i32)
+    {
+        if half_width != self.half_width || self.cover_times.len() != walkers.len() {
+            self.half_width = half_width;
+            self.cover_times = vec![None; walkers.len()];
+        }
+        let area = box_area(half_width);
+        self.fractions = walkers.par_iter()
+            .zip(self.cover_times.par_iter_mut())
+            .map(
+                |(walker, time)|
+                {
+                    let sites = sites_in_box(&walker.history, half_width);
+                    // Besucht bleibt besucht - die Zeit muss nur einmal gesucht werden
+                    if sites == area && time.is_none() {
+                        *time = cover_time(&walker.history, half_width);
+                    }
+                    sites as f64 / area as f64
+                }
+            ).collect();
+    }
+
+    /// Zeigt die Abdeckung des Walkers `idx` und den Mittelwert aller Walker an.
+    /// Die Bedeckungszeit wird nur angezeigt, wenn die Walker im Kasten eingesperrt sind
+    pub fn ui(&self, ui: &mut egui::Ui, idx: usize, show_cover_time: bool)
+    {
+        if self.fractions.is_empty() {
+            return;
+        }
+        let side = 2 * self.half_width + 1;
+        let mean = self.fractions.iter().sum::<f64>() / self.fractions.len() as f64;
+        ui.label(
+            format!(
+                "Abdeckung des Kastens ({side}×{side} Felder): Walker {idx}: {:.1} %, Mittel: {:.1} %",
+                100.0 * self.fractions[idx],
+                100.0 * mean
+            )
+        );
+        if show_cover_time {
+            let own = match self.cover_times[idx]{
+                Some(time) => time.to_string(),
+                None => "noch nicht".to_owned()
+            };
+            let finished: Vec<usize> = self.cover_times.iter().flatten().copied().collect();
+            let mean = if finished.is_empty() {
+                "-".to_owned()
+            } else {
+                format!("{:.0}", finished.iter().sum::<usize>() as f64 / finished.len() as f64)
+            };
+            ui.label(
+                format!(
+                    "Bedeckungszeit: Walker {idx}: {own}, Mittel: {mean} ({} von {} Walkern fertig)",
+                    finished.len(),
+                    self.cover_times.len()
+                )
+            ).on_hover_text("Nach wie vielen Schritten alle Felder im Kasten besucht waren. Der Mittelwert enthält nur die Walker, die schon fertig sind");
+        }
+    }
+}
@@ -0,0 +1,478 @@
+use egui::{
+    plot::*,
+    Color32,
+    Mesh,
+    Pos2,
+    Rect,
+    Sense,
+    Vec2
+};
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+
+use crate::random_walker::{AverageDistance, Position, RandomWalker};
+use crate::walk_model::{RadioState, WalkModel};
+
+/// Wie viele Zellen die Heatmap höchstens pro Richtung hat
+const MAX_HEATMAP_CELLS: i32 = 101;
+/// Wie viele Balken die Histogramme ungefähr haben
+const HISTOGRAM_BINS: usize = 30;
+/// Damit die Oberfläche flüssig bleibt, werden pro Bild höchstens so viele
+/// Schritte aller Walker zusammen gemacht. Das gilt für alles, was nebenbei rechnet
+pub const MAX_WALKER_STEPS_PER_UPDATE: usize = 500_000;
+
+/// Erzeugt `num_of_walkers` Walker am Ursprung. Die RNGs der Walker werden
+/// aus einem mit `seed` initialisierten RNG gezogen
+pub fn create_walkers(seed: u64, num_of_walkers: usize, capacity: usize) -> Vec<RandomWalker>
+{
+    walker_rngs(seed, num_of_walkers)
+        .map(|rng| RandomWalker::with_capacity_and_rng(rng, capacity))
+        .collect()
+}
+
+/// Wie [`create_walkers`], aber nur der Walker `focus` speichert seine ganze Spur.
+/// Alle anderen kennen nur ihre aktuelle Position, siehe
+/// [`crate::random_walker::History::without_trajectory`]. Die Walker laufen genau
+/// gleich wie die von [`create_walkers`], nur die besuchten Felder S(t) fehlen.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::ensemble::{create_walkers, create_streaming_walkers};
+/// use girls_day::random_walker::AverageDistance;
+/// use girls_day::walk_model::WalkModel;
+/// let mut model = WalkModel::default();
+/// let mut full = create_walkers(7, 50, 3000);
+/// let mut streaming = create_streaming_walkers(7, 50, 3000, Some(3));
+/// let mut full_average = AverageDistance::default();
+/// let mut streaming_average = AverageDistance::default();
+/// model.step_and_measure(&mut full, 3000, &mut full_average);
+/// model.step_and_measure(&mut streaming, 3000, &mut streaming_average);
+/// for t in [0, 1, 1500, 2999] {
+///     assert_eq!(full_average.distance[t].mean(), streaming_average.distance[t].mean());
+///     assert_eq!(full_average.hull_area[t].mean(), streaming_average.hull_area[t].mean());
+///     assert_eq!(full_average.radius_of_gyration[t].mean(), streaming_average.radius_of_gyration[t].mean());
+/// }
+/// assert!(streaming_average.distinct_sites.is_empty());
+/// assert_eq!(streaming[0].ort, full[0].ort);
+/// // Nur der Walker im Fokus hat noch seine Spur
+/// assert_eq!(streaming[3].history.positions(0).count(), 3000);
+/// assert_eq!(streaming[0].history.positions(0).count(), 0);
+/// ```
+pub fn create_streaming_walkers(seed: u64, num_of_walkers: usize, capacity: usize, focus: Option<usize>) -> Vec<RandomWalker>
+{
+    walker_rngs(seed, num_of_walkers)
+        .enumerate()
+        .map(
+            |(i, rng)|
+            {
+                if focus == Some(i) {
+                    RandomWalker::with_capacity_and_rng(rng, capacity)
+                } else {
+                    RandomWalker::without_trajectory(rng)
+                }
+            }
+        ).collect()
+}
+
+/// Wie viele Schritte `num_of_walkers` Walker in diesem Bild machen, wenn ihnen
+/// noch `remaining` Schritte fehlen, siehe [`MAX_WALKER_STEPS_PER_UPDATE`]
+pub fn steps_per_update(num_of_walkers: usize, remaining: usize) -> usize
+{
+    (MAX_WALKER_STEPS_PER_UPDATE / num_of_walkers.max(1))
+        .max(1)
+        .min(remaining)
+}
+
+/// Ein Lauf neuer Walker ohne Spur, der pro Bild ein Stück weiterläuft,
+/// bis alle Walker `steps` Schritte gemacht haben. Unterwegs sammelt er ihre Statistiken.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::ensemble::{Batch, BatchProgress};
+/// use girls_day::walk_model::WalkModel;
+/// let mut model = WalkModel::default();
+/// let mut batch = Batch::new(3, 1000, 2000);
+/// let mut updates = 1;
+/// while batch.advance(&mut model) == BatchProgress::Running {
+///     updates += 1;
+/// }
+/// assert_eq!(updates, 4);
+/// assert_eq!(batch.stats.distance.len(), 2000);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Batch{
+    pub walkers: Vec<RandomWalker>,
+    pub stats: AverageDistance,
+    steps: usize
+}
+
+/// Wie weit ein [`Batch`] nach [`Batch::advance`] ist
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BatchProgress{
+    Running,
+    Finished,
+    /// Die Schrittregel ist ungültig oder hat unterwegs einen Fehler gemeldet
+    Failed
+}
+
+impl Batch{
+    pub fn new(seed: u64, num_of_walkers: usize, steps: usize) -> Self
+    {
+        Self{
+            walkers: create_streaming_walkers(seed, num_of_walkers, 0, None),
+            stats: AverageDistance::default(),
+            steps
+        }
+    }
+
+    /// Macht die Schritte für dieses Bild, siehe [`steps_per_update`]
+    pub fn advance(&mut self, model: &mut WalkModel) -> BatchProgress
+    {
+        let done = self.walkers[0].history.len();
+        let steps = steps_per_update(self.walkers.len(), self.steps - done);
+        if model.step_and_measure(&mut self.walkers, steps as u64, &mut self.stats) < steps as u64 {
+            return BatchProgress::Failed;
+        }
+        if done + steps >= self.steps {
+            BatchProgress::Finished
+        } else {
+            BatchProgress::Running
+        }
+    }
+}
+
+/// Die RNGs der Walker, alle aus einem mit `seed` initialisierten RNG gezogen
+fn walker_rngs(seed: u64, num_of_walkers: usize) -> impl Iterator<Item = Pcg64>
+{
+    let mut pcg = Pcg64::seed_from_u64(seed);
+    (0..num_of_walkers).map(move |_| Pcg64::from_rng(&mut pcg).unwrap())
+}
+
+/// Die Positionen der Walker aus vielen unabhängigen Läufen,
+/// alle nach der gleichen Anzahl an Schritten
+#[derive(Debug, Clone, Default)]
+pub struct AccumulatedPositions{
+    pub step: usize,
+    pub runs: usize,
+    pub positions: Vec<Position>
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum EnsembleSource{
+    Current,
+    Accumulated
+}
+
+/// Die zweite Ansicht: Wo sind alle Walker gerade?
+#[derive(Debug, Clone)]
+pub struct EnsembleView{
+    pub source: EnsembleSource,
+    pub runs_to_add: usize,
+    pending_runs: usize,
+    next_run: u64,
+    /// Der Lauf, der gerade simuliert wird
+    batch: Option<Batch>,
+    pub accumulated: AccumulatedPositions
+}
+
+impl Default for EnsembleView{
+    fn default() -> Self {
+        Self{
+            source: EnsembleSource::Current,
+            runs_to_add: 20,
+            pending_runs: 0,
+            next_run: 1,
+            batch: None,
+            accumulated: AccumulatedPositions::default()
+        }
+    }
+}
+
+impl EnsembleView{
+    /// Vergisst alle gesammelten Läufe
+    pub fn reset(&mut self)
+    {
+        self.pending_runs = 0;
+        self.next_run = 1;
+        self.batch = None;
+        self.accumulated = AccumulatedPositions::default();
+    }
+
+    pub fn is_busy(&self) -> bool
+    {
+        self.pending_runs > 0
+    }
+
+    /// Simuliert die angeforderten Läufe nacheinander. Damit die Oberfläche nicht hängt,
+    /// werden pro Bild höchstens [`MAX_WALKER_STEPS_PER_UPDATE`] Schritte aller Walker
+    /// zusammen gemacht. Die Walker speichern keine Spur, am Ende des Laufs zählt nur
+    /// ihre Position.
+    ///
+    /// Jeder Lauf bekommt seinen eigenen seed: seed + 1, seed + 2, ...
+    pub fn run_pending(&mut self, model: &mut WalkModel, seed: u64, num_of_walkers: usize)
+    {
+        if self.pending_runs == 0 {
+            return;
+        }
+        let step = self.accumulated.step;
+        let next_run = self.next_run;
+        let batch = self.batch.get_or_insert_with(|| Batch::new(seed.wrapping_add(next_run), num_of_walkers, step));
+        match batch.advance(model) {
+            BatchProgress::Running => {},
+            BatchProgress::Failed => {
+                self.pending_runs = 0;
+                self.batch = None;
+            },
+            BatchProgress::Finished => {
+                let batch = self.batch.take().unwrap();
+                self.next_run += 1;
+                self.pending_runs -= 1;
+                self.accumulated.runs += 1;
+                self.accumulated.positions
+                    .extend(batch.walkers.into_iter().map(|walker| walker.ort));
+            }
+        }
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        walkers: &[RandomWalker],
+        model: &WalkModel,
+        color_low: Color32,
+        color_high: Color32
+    )
+    {
+        let current_step = walkers[0].history.len();
+        // Die Kurven gelten nur für den normalen Random Walk ohne Kasten
+        let normal_walk = model.radio == RadioState::NoBias && !model.box_enabled;
+        ui.horizontal(
+            |ui|
+            {
+                ui.selectable_value(&mut self.source, EnsembleSource::Current, "Aktuelle Walker");
+                ui.selectable_value(&mut self.source, EnsembleSource::Accumulated, "Gesammelte Läufe");
+                ui.separator();
+                ui.add(egui::DragValue::new(&mut self.runs_to_add).clamp_range(1..=10000));
+                let button = egui::Button::new(format!("weitere Läufe bis Schritt {current_step}"));
+                if ui.add_enabled(!self.is_busy(), button)
+                    .on_hover_text("Simuliert neue, unabhängige Walker mit den aktuellen Einstellungen bis zum aktuellen Schritt und sammelt ihre Positionen")
+                    .clicked()
+                {
+                    if self.accumulated.step != current_step {
+                        self.reset();
+                        self.accumulated.step = current_step;
+                    }
+                    self.pending_runs = self.runs_to_add;
+                    self.source = EnsembleSource::Accumulated;
+                }
+                if self.is_busy() {
+                    ui.spinner();
+                    ui.label(format!("noch {} Läufe", self.pending_runs));
+                }
+                if ui.button("Vergessen").clicked(){
+                    self.reset();
+                }
+            }
+        );
+
+        let current: Vec<Position>;
+        let (positions, step) = match self.source{
+            EnsembleSource::Current => {
+                current = walkers.iter().map(|walker| walker.ort.clone()).collect();
+                ui.label(format!("{} Walker bei Schritt {current_step}", current.len()));
+                (current.as_slice(), current_step)
+            },
+            EnsembleSource::Accumulated => {
+                ui.label(
+                    format!(
+                        "{} Walker aus {} Läufen bei Schritt {}",
+                        self.accumulated.positions.len(),
+                        self.accumulated.runs,
+                        self.accumulated.step
+                    )
+                );
+                (self.accumulated.positions.as_slice(), self.accumulated.step)
+            }
+        };
+
+        if positions.is_empty() {
+            ui.label("Noch keine Daten");
+            return;
+        }
+
+        ui.horizontal_top(
+            |ui|
+            {
+                let size = ui.available_width().min(ui.available_height()) * 0.45;
+                let (response, painter) = ui.allocate_painter(Vec2::splat(size), Sense::hover());
+                painter.rect_filled(response.rect, 0.0, Color32::BLACK);
+                painter.add(heatmap_mesh(positions, response.rect, color_low, color_high));
+
+                ui.vertical(
+                    |ui|
+                    {
+                        let height = (ui.available_height() / 2.0 - 20.0).max(50.0);
+                        ui.label("Abstand vom Ursprung");
+                        distance_histogram_plot(ui, positions, step, normal_walk, height);
+                        ui.label("x-Koordinate");
+                        x_histogram_plot(ui, positions, step, normal_walk, height);
+                    }
+                );
+            }
+        );
+    }
+}
+
+/// Zeichnet wie oft die Felder besetzt sind. Sind es zu viele Felder,
+/// werden mehrere Felder zu einer Zelle zusammengefasst
+pub fn heatmap_mesh(
+    positions: &[Position],
+    rect: Rect,
+    color_low: Color32,
+    color_high: Color32
+) -> Mesh
+{
+    let mut mesh = Mesh::default();
+    let radius = positions.iter()
+        .map(|pos| pos.x.abs().max(pos.y.abs()))
+        .max()
+        .unwrap_or(0);
+    let sites = 2 * radius + 1;
+    let sites_per_cell = (sites + MAX_HEATMAP_CELLS - 1) / MAX_HEATMAP_CELLS;
+    // Ungerade Anzahl an Zellen, damit der Ursprung in der Mitte liegt
+    let half_cells = (radius + sites_per_cell / 2) / sites_per_cell;
+    let cells = 2 * half_cells + 1;
+
+    let mut counts = vec![0_u32; (cells * cells) as usize];
+    let cell_of = |v: i32| (v + sites_per_cell / 2).div_euclid(sites_per_cell) + half_cells;
+    for pos in positions{
+        let cx = cell_of(pos.x).clamp(0, cells - 1);
+        let cy = cell_of(pos.y).clamp(0, cells - 1);
+        counts[(cy * cells + cx) as usize] += 1;
+    }
+    let max = counts.iter().copied().max().unwrap_or(1).max(1) as f32;
+
+    let cell_size = rect.width() / cells as f32;
+    for cy in 0..cells{
+        for cx in 0..cells{
+            let count = counts[(cy * cells + cx) as usize];
+            if count == 0 {
+                continue;
+            }
+            // Wurzel, damit auch selten besuchte Zellen sichtbar sind
+            let p = (count as f32 / max).sqrt();
+            let min = Pos2{
+                x: rect.min.x + cx as f32 * cell_size,
+                y: rect.min.y + cy as f32 * cell_size
+            };
+            mesh.add_colored_rect(
+                Rect::from_min_size(min, Vec2::splat(cell_size)),
+                lerp_color(color_low, color_high, p)
+            );
+        }
+    }
+    mesh
+}
+
+pub fn lerp_color(a: Color32, b: Color32, p: f32) -> Color32
+{
+    let lerp = |a: u8, b: u8| (a as f32 + p * (b as f32 - a as f32)) as u8;
+    Color32::from_rgb(lerp(a.r(), b.r()), lerp(a.g(), b.g()), lerp(a.b(), b.b()))
+}
+
+/// Normiertes Histogramm: Die Fläche aller Balken ist 1
+pub fn histogram(values: &[f64], min: f64, bin_width: f64, bins: usize) -> Vec<Bar>
+{
+    let mut counts = vec![0_usize; bins];
+    for v in values{
+        let bin = ((v - min) / bin_width).floor();
+        if bin >= 0.0 && (bin as usize) < bins {
+            counts[bin as usize] += 1;
+        }
+    }
+    let norm = 1.0 / (values.len() as f64 * bin_width);
+    counts.into_iter()
+        .enumerate()
+        .map(
+            |(i, count)|
+            {
+                Bar::new(min + (i as f64 + 0.5) * bin_width, count as f64 * norm)
+                    .width(bin_width)
+            }
+        ).collect()
+}
+
+/// 201 Punkte der Funktion f zwischen min und max
+pub fn curve(min: f64, max: f64, f: impl Fn(f64) -> f64) -> Vec<PlotPoint>
+{
+    (0..=200)
+        .map(
+            |i|
+            {
+                let x = min + (max - min) * i as f64 / 200.0;
+                PlotPoint { x, y: f(x) }
+            }
+        ).collect()
+}
+
+/// Histogramm der Abstände vom Ursprung. Für den normalen Random Walk
+/// ist der Abstand nach t Schritten Rayleigh-verteilt mit σ² = t/2,
+/// diese Kurve wird mit `normal_walk` eingezeichnet
+fn distance_histogram_plot(ui: &mut egui::Ui, positions: &[Position], step: usize, normal_walk: bool, height: f32)
+{
+    let distances: Vec<f64> = positions.iter()
+        .map(|pos| ((pos.x as f64).powi(2) + (pos.y as f64).powi(2)).sqrt())
+        .collect();
+    let max = distances.iter().copied().fold(1.0, f64::max);
+    let bin_width = max / HISTOGRAM_BINS as f64;
+    let bars = histogram(&distances, 0.0, bin_width, HISTOGRAM_BINS + 1);
+
+    let sigma2 = step as f64 / 2.0;
+    let rayleigh = curve(0.0, max + bin_width, |r| r / sigma2 * (-r * r / (2.0 * sigma2)).exp());
+
+    Plot::new("ensemble_distance_histogram")
+        .legend(Legend::default())
+        .height(height)
+        .show(
+            ui,
+            |plot_ui|
+            {
+                plot_ui.bar_chart(BarChart::new(bars).name("Walker"));
+                if normal_walk && step > 0 {
+                    plot_ui.line(Line::new(PlotPoints::Owned(rayleigh)).name("Rayleigh (normaler Random Walk)"));
+                }
+            }
+        );
+}
+
+/// Histogramm der x-Koordinate. Für den normalen Random Walk
+/// ist sie gaußverteilt mit σ² = t/2, diese Kurve wird mit `normal_walk` eingezeichnet
+fn x_histogram_plot(ui: &mut egui::Ui, positions: &[Position], step: usize, normal_walk: bool, height: f32)
+{
+    let xs: Vec<f64> = positions.iter()
+        .map(|pos| pos.x as f64)
+        .collect();
+    let max = xs.iter().map(|x| x.abs()).fold(1.0, f64::max);
+    // Balken sind mindestens ein Feld breit
+    let bin_width = (2.0 * max / HISTOGRAM_BINS as f64).ceil().max(1.0);
+    let bins = (2.0 * max / bin_width).ceil() as usize + 1;
+    let min = -(bins as f64) * bin_width / 2.0;
+    let bars = histogram(&xs, min, bin_width, bins);
+
+    let sigma2 = step as f64 / 2.0;
+    let gauss = curve(min, -min, |x| (-x * x / (2.0 * sigma2)).exp() / (2.0 * std::f64::consts::PI * sigma2).sqrt());
+
+    Plot::new("ensemble_x_histogram")
+        .legend(Legend::default())
+        .height(height)
+        .show(
+            ui,
+            |plot_ui|
+            {
+                plot_ui.bar_chart(BarChart::new(bars).name("Walker"));
+                if normal_walk && step > 0 {
+                    plot_ui.line(Line::new(PlotPoints::Owned(gauss)).name("Gauß (normaler Random Walk)"));
+                }
+            }
+        );
+}
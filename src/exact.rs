@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use egui::plot::PlotPoint;
+
+use crate::random_walker::Position;
+use crate::walk_model::{RadioState, WalkModel};
+
+/// Bis zu so vielen Schritten wird exakt gerechnet, wenn die Schritte nur vom Ort abhängen
+pub const MAX_STEPS: usize = 200;
+/// Beim selbstabstoßenden Walker müssen wirklich alle 4^n Walks einzeln aufgezählt werden
+pub const MAX_STEPS_SELF_AVOIDING: usize = 10;
+
+/// Exakte Mittelwerte über alle möglichen Walks, für t = 0 bis n Schritte
+#[derive(Debug, Clone, Default)]
+pub struct ExactResult{
+    pub mean_distance: Vec<f64>,
+    pub msd: Vec<f64>,
+    /// Wahrscheinlichkeit, nach t Schritten im Ursprung zu sein
+    pub at_origin: Vec<f64>,
+    /// Wahrscheinlichkeit, bis zum Schritt t schon zum Ursprung zurückgekehrt zu sein
+    pub returned: Vec<f64>
+}
+
+impl ExactResult{
+    fn with_capacity(steps: usize) -> Self
+    {
+        Self{
+            mean_distance: vec![0.0; steps + 1],
+            msd: vec![0.0; steps + 1],
+            at_origin: vec![0.0; steps + 1],
+            returned: vec![0.0; steps + 1]
+        }
+    }
+
+    /// Der Walk ist mit Wahrscheinlichkeit `p` nach `t` Schritten bei `pos`,
+    /// davon ist er mit Wahrscheinlichkeit `returned` schon einmal zurückgekehrt
+    fn add(&mut self, t: usize, pos: &Position, p: f64, returned: f64)
+    {
+        let r2 = pos.x as f64 * pos.x as f64 + pos.y as f64 * pos.y as f64;
+        self.mean_distance[t] += p * r2.sqrt();
+        self.msd[t] += p * r2;
+        if r2 == 0.0 {
+            self.at_origin[t] += p;
+        }
+        self.returned[t] += returned;
+    }
+}
+
+/// Wohin der Walker wirklich kommt: Ein Schritt aus dem Kasten heraus wird
+/// zurückgenommen, wie in [`crate::random_walker::RandomWalker::keep_inside_box`]
+fn target(pos: &Position, (dx, dy): (i32, i32), boundary: Option<i32>) -> Position
+{
+    let next = Position { x: pos.x + dx, y: pos.y + dy };
+    let inside = |p: &Position, half_width: i32| p.x.abs() <= half_width && p.y.abs() <= half_width;
+    match boundary{
+        Some(half_width) if inside(pos, half_width) && !inside(&next, half_width) => pos.clone(),
+        _ => next
+    }
+}
+
+/// Rechnet exakt aus, wie sich die Walker des Modells in den ersten `steps` Schritten
+/// im Mittel verhalten - ohne Zufall, indem alle Walks mit ihren Wahrscheinlichkeiten
+/// durchgegangen werden.
+///
+/// Hängen die Schritte nur vom Ort ab, genügt es, für jeden Schritt die Wahrscheinlichkeit
+/// jedes Ortes zu kennen (und ob der Walker schon zurückgekehrt war). Das ist genauso exakt
+/// wie alle 4^n Walks einzeln aufzuzählen, aber viel schneller.
+/// Beim selbstabstoßenden Walker werden dagegen wirklich alle Walks aufgezählt.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::exact::exact_statistics;
+/// use girls_day::walk_model::{WalkModel, RadioState};
+/// let model = WalkModel::default();
+/// let exact = exact_statistics(&model, 20).unwrap();
+/// // Für den normalen Random Walk ist das mittlere Abstandsquadrat genau t
+/// assert!((exact.msd[20] - 20.0).abs() < 1e-9);
+/// // Nach 2 Schritten ist er mit Wahrscheinlichkeit 4/16 wieder im Ursprung
+/// assert!((exact.at_origin[2] - 0.25).abs() < 1e-12);
+/// assert!((exact.mean_distance[1] - 1.0).abs() < 1e-12);
+///
+/// // Ohne Abstoßung ist der selbstabstoßende Walker ein normaler Random Walk
+/// let tsaw = WalkModel { radio: RadioState::TrueSelfAvoiding, repulsion: 0.0, ..WalkModel::default() };
+/// let enumerated = exact_statistics(&tsaw, 6).unwrap();
+/// for t in 0..=6 {
+///     assert!((enumerated.mean_distance[t] - exact.mean_distance[t]).abs() < 1e-12);
+///     assert!((enumerated.returned[t] - exact.returned[t]).abs() < 1e-12);
+/// }
+/// ```
+///
+/// Die Simulation muss im Rahmen ihres Fehlers mit dem exakten Ergebnis übereinstimmen:
+/// ```
+/// use girls_day::exact::exact_statistics;
+/// use girls_day::ensemble::create_walkers;
+/// use girls_day::random_walker::AverageDistance;
+/// use girls_day::walk_model::{WalkModel, RadioState};
+/// let mut model = WalkModel { radio: RadioState::BiasedAwayFromOrigin, strength_of_bias: 0.2, ..WalkModel::default() };
+/// let exact = exact_statistics(&model, 15).unwrap();
+/// let mut walkers = create_walkers(12, 2000, 15);
+/// model.step_walkers(&mut walkers, 15);
+/// let mut average = AverageDistance::default();
+/// average.update_on_step_of_walkers(15, &walkers);
+/// for t in [2, 5, 14] {
+///     let w = &average.distance[t];
+///     assert!((w.mean() - exact.mean_distance[t]).abs() < 5.0 * w.std_error());
+/// }
+/// ```
+pub fn exact_statistics(model: &WalkModel, steps: usize) -> Result<ExactResult, String>
+{
+    if model.radio == RadioState::TrueSelfAvoiding {
+        if steps > MAX_STEPS_SELF_AVOIDING {
+            return Err(format!("Beim selbstabstoßenden Walker gehen höchstens {MAX_STEPS_SELF_AVOIDING} Schritte"));
+        }
+        let mut result = ExactResult::with_capacity(steps);
+        let mut visits = HashMap::new();
+        enumerate_self_avoiding(model, &mut result, &mut visits, Position { x: 0, y: 0 }, 0, steps, 1.0, false);
+        return Ok(result);
+    }
+    if steps > MAX_STEPS {
+        return Err(format!("Es gehen höchstens {MAX_STEPS} Schritte"));
+    }
+    let boundary = model.boundary();
+    let mut result = ExactResult::with_capacity(steps);
+    // Pro Ort: Wahrscheinlichkeit noch nicht / schon zurückgekehrt
+    let mut current: HashMap<Position, [f64; 2]> = HashMap::new();
+    current.insert(Position { x: 0, y: 0 }, [1.0, 0.0]);
+    for t in 0..=steps{
+        for (pos, [fresh, returned]) in &current{
+            result.add(t, pos, fresh + returned, *returned);
+        }
+        if t == steps {
+            break;
+        }
+        let mut next: HashMap<Position, [f64; 2]> = HashMap::with_capacity(current.len() * 2);
+        for (pos, [fresh, returned]) in &current{
+            for (step, p) in model.transition_probabilities(pos)?{
+                let new = target(pos, step, boundary);
+                let entry = next.entry(new.clone()).or_default();
+                if new.x == 0 && new.y == 0 {
+                    entry[1] += (fresh + returned) * p;
+                } else {
+                    entry[0] += fresh * p;
+                    entry[1] += returned * p;
+                }
+            }
+        }
+        current = next;
+    }
+    Ok(result)
+}
+
+/// Geht alle Walks des selbstabstoßenden Walkers der Reihe nach durch.
+/// `visits` wird dabei verändert und am Ende wiederhergestellt
+#[allow(clippy::too_many_arguments)]
+fn enumerate_self_avoiding(
+    model: &WalkModel,
+    result: &mut ExactResult,
+    visits: &mut HashMap<Position, u32>,
+    pos: Position,
+    t: usize,
+    steps: usize,
+    p: f64,
+    returned: bool
+)
+{
+    result.add(t, &pos, p, if returned { p } else { 0.0 });
+    if t == steps {
+        return;
+    }
+    let Position { x, y } = pos;
+    let mut neighbors = vec![Position { x: x + 1, y }, Position { x: x - 1, y }];
+    if !model.one_dimensional {
+        neighbors.extend([Position { x, y: y + 1 }, Position { x, y: y - 1 }]);
+    }
+    *visits.entry(pos.clone()).or_insert(0) += 1;
+    let counts: Vec<u32> = neighbors.iter()
+        .map(|n| visits.get(n).copied().unwrap_or(0))
+        .collect();
+    // Wie beim Walker selbst: das Minimum abziehen, damit exp() nicht 0 wird
+    let min = counts.iter().copied().min().unwrap_or(0);
+    let weights: Vec<f64> = counts.iter()
+        .map(|c| (-model.repulsion * (c - min) as f64).exp())
+        .collect();
+    let total: f64 = weights.iter().sum();
+    for (neighbor, weight) in neighbors.iter().zip(weights){
+        let step = (neighbor.x - x, neighbor.y - y);
+        let next = target(&pos, step, model.boundary());
+        let back_home = next.x == 0 && next.y == 0;
+        enumerate_self_avoiding(model, result, visits, next, t + 1, steps, p * weight / total, returned || back_home);
+    }
+    *visits.get_mut(&pos).unwrap() -= 1;
+}
+
+/// Zeigt die exakten Werte für kurze Walks zusätzlich im Plot an,
+/// damit man sieht, ob die Simulation stimmt
+#[derive(Debug, Clone)]
+pub struct ExactOverlay{
+    pub enabled: bool,
+    pub steps: usize,
+    /// Wird erst berechnet, wenn es gebraucht wird
+    result: Option<Result<ExactResult, String>>
+}
+
+impl Default for ExactOverlay{
+    fn default() -> Self {
+        Self{
+            enabled: false,
+            steps: 20,
+            result: None
+        }
+    }
+}
+
+impl ExactOverlay{
+    /// Vergisst das Ergebnis, z.B. weil sich das Modell geändert hat
+    pub fn reset(&mut self)
+    {
+        self.result = None;
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &WalkModel)
+    {
+        let toggled = ui.checkbox(&mut self.enabled, "Exakt")
+            .on_hover_text("Berechnet den Mittelwert für kurze Walks exakt, indem alle Walks mit ihren Wahrscheinlichkeiten durchgegangen werden")
+            .changed();
+        if !self.enabled {
+            return;
+        }
+        let max = if model.radio == RadioState::TrueSelfAvoiding { MAX_STEPS_SELF_AVOIDING } else { MAX_STEPS };
+        let changed = ui.add(egui::DragValue::new(&mut self.steps).clamp_range(1..=max).suffix(" Schritte")).changed();
+        let recompute = ui.button("neu berechnen")
+            .on_hover_text("Nach einer Änderung am Modell")
+            .clicked();
+        if toggled || changed || recompute {
+            self.reset();
+        }
+        let steps = self.steps;
+        let result = self.result.get_or_insert_with(|| exact_statistics(model, steps));
+        match result{
+            Ok(exact) => {
+                ui.label(format!("Rückkehr bis Schritt {steps}: {:.4}", exact.returned[steps]))
+                    .on_hover_text("Die exakte Wahrscheinlichkeit, bis dahin mindestens einmal zum Ursprung zurückgekehrt zu sein");
+            },
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, e.as_str());
+            }
+        }
+    }
+
+    /// Der exakte mittlere Abstand oder das mittlere Abstandsquadrat, falls berechnet
+    pub fn curve(&self, squared: bool) -> Option<Vec<PlotPoint>>
+    {
+        let exact = match &self.result{
+            Some(Ok(exact)) if self.enabled => exact,
+            _ => return None
+        };
+        let values = if squared { &exact.msd } else { &exact.mean_distance };
+        let points = values.iter()
+            .enumerate()
+            .map(|(t, y)| PlotPoint { x: t as f64, y: *y })
+            .collect();
+        Some(points)
+    }
+}
@@ -0,0 +1,274 @@
+use egui::plot::*;
+
+use crate::power_law::log_axis_label;
+use crate::random_walker::{Position, RandomWalker};
+
+/// Wie viele Balken das Histogramm der Zeiten höchstens hat
+const HISTOGRAM_BINS: usize = 30;
+
+/// Was der Walker erreichen soll
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PassageTarget{
+    /// Ein bestimmtes Feld
+    Site,
+    /// Irgendein Feld mit mindestens diesem Abstand vom Ursprung
+    Distance
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PassageQuantity{
+    Return,
+    Target
+}
+
+/// Wann jeder Walker zum ersten Mal zum Ursprung zurückgekehrt ist
+/// und wann er zum ersten Mal das Ziel erreicht hat.
+///
+/// Die Historie wird nur einmal durchsucht: Bei jedem Aufruf von
+/// [`FirstPassage::update`] kommen nur die neuen Schritte dazu.
+/// Walker, bei denen es bis zum aktuellen Schritt noch nicht passiert ist,
+/// sind zensiert - sie werden nicht weggelassen, sondern gezählt.
+#[derive(Debug, Clone)]
+pub struct FirstPassage{
+    pub quantity: PassageQuantity,
+    pub target: PassageTarget,
+    pub target_site: (i32, i32),
+    pub target_distance: f64,
+    return_times: Vec<Option<usize>>,
+    return_checked: usize,
+    target_times: Vec<Option<usize>>,
+    target_checked: usize
+}
+
+impl Default for FirstPassage{
+    fn default() -> Self {
+        Self{
+            quantity: PassageQuantity::Return,
+            target: PassageTarget::Distance,
+            target_site: (5, 0),
+            target_distance: 10.0,
+            return_times: Vec::new(),
+            return_checked: 0,
+            target_times: Vec::new(),
+            target_checked: 0
+        }
+    }
+}
+
+/// Durchsucht die Schritte ab `checked` und merkt sich bei jedem Walker
+/// den ersten Schritt, bei dem `reached` gilt
+fn scan(
+    walkers: &[RandomWalker],
+    times: &mut Vec<Option<usize>>,
+    checked: &mut usize,
+    reached: impl Fn(usize, &Position) -> bool
+)
+{
+    let len = walkers[0].history.len();
+    if times.len() != walkers.len() || *checked > len {
+        *times = vec![None; walkers.len()];
+        *checked = 0;
+    }
+    for (walker, time) in walkers.iter().zip(times.iter_mut()){
+        if time.is_some() {
+            continue;
+        }
+        *time = walker.history.positions(*checked)
+            .take(len - *checked)
+            .zip(*checked..)
+            .find(|(pos, t)| reached(*t, pos))
+            .map(|(_, t)| t);
+    }
+    *checked = len;
+}
+
+impl FirstPassage{
+    pub fn reset(&mut self)
+    {
+        self.reset_return();
+        self.reset_target();
+    }
+
+    fn reset_return(&mut self)
+    {
+        self.return_times.clear();
+        self.return_checked = 0;
+    }
+
+    fn reset_target(&mut self)
+    {
+        self.target_times.clear();
+        self.target_checked = 0;
+    }
+
+    fn target_reached(&self, pos: &Position) -> bool
+    {
+        match self.target{
+            PassageTarget::Site => (pos.x, pos.y) == self.target_site,
+            PassageTarget::Distance => {
+                let r2 = pos.x as f64 * pos.x as f64 + pos.y as f64 * pos.y as f64;
+                r2 >= self.target_distance * self.target_distance
+            }
+        }
+    }
+
+    /// Nimmt die neuen Schritte aller Walker mit
+    pub fn update(&mut self, walkers: &[RandomWalker])
+    {
+        // Der Start im Ursprung zählt nicht als Rückkehr
+        scan(
+            walkers,
+            &mut self.return_times,
+            &mut self.return_checked,
+            |t, pos| t > 0 && pos.x == 0 && pos.y == 0
+        );
+        let mut target_times = std::mem::take(&mut self.target_times);
+        let mut target_checked = self.target_checked;
+        scan(walkers, &mut target_times, &mut target_checked, |_, pos| self.target_reached(pos));
+        self.target_times = target_times;
+        self.target_checked = target_checked;
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, walkers: &[RandomWalker])
+    {
+        ui.horizontal(
+            |ui|
+            {
+                ui.selectable_value(&mut self.quantity, PassageQuantity::Return, "Rückkehr zum Ursprung");
+                ui.selectable_value(&mut self.quantity, PassageQuantity::Target, "Ziel erreicht");
+                if self.quantity == PassageQuantity::Target {
+                    ui.separator();
+                    let mut changed = ui.radio_value(&mut self.target, PassageTarget::Site, "Feld").changed();
+                    if self.target == PassageTarget::Site {
+                        ui.label("x");
+                        changed |= ui.add(egui::DragValue::new(&mut self.target_site.0)).changed();
+                        ui.label("y");
+                        changed |= ui.add(egui::DragValue::new(&mut self.target_site.1)).changed();
+                    }
+                    changed |= ui.radio_value(&mut self.target, PassageTarget::Distance, "Abstand R").changed();
+                    if self.target == PassageTarget::Distance {
+                        changed |= ui.add(
+                            egui::DragValue::new(&mut self.target_distance)
+                                .clamp_range(1.0..=10000.0)
+                                .speed(0.5)
+                        ).changed();
+                    }
+                    if changed {
+                        self.reset_target();
+                    }
+                }
+            }
+        );
+        self.update(walkers);
+
+        let step = self.return_checked;
+        let times = match self.quantity{
+            PassageQuantity::Return => &self.return_times,
+            PassageQuantity::Target => &self.target_times
+        };
+        let mut finished: Vec<usize> = times.iter().flatten().copied().collect();
+        finished.sort_unstable();
+        let censored = times.len() - finished.len();
+        ui.label(
+            format!(
+                "{} von {} Walkern angekommen, {censored} zensiert (bis Schritt {step} noch nicht angekommen)",
+                finished.len(),
+                times.len()
+            )
+        ).on_hover_text("Zensierte Walker werden nicht weggelassen: Sie zählen im Histogramm zur Gesamtzahl und in der Überlebenswahrscheinlichkeit als noch nicht angekommen");
+
+        if times.is_empty() || step < 2 {
+            ui.label("Noch keine Daten");
+            return;
+        }
+
+        let height = (ui.available_height() / 2.0 - 20.0).max(50.0);
+        ui.label("Anteil der Walker pro Zeitintervall (logarithmische Zeitachse)");
+        time_histogram_plot(ui, &finished, times.len(), step, height);
+        ui.label("Überlebenswahrscheinlichkeit: Anteil der Walker, die bis t noch nicht angekommen sind");
+        survival_plot(ui, &finished, times.len(), step, height, self.quantity == PassageQuantity::Return);
+    }
+}
+
+/// Histogramm über log10(t). Die Höhe ist der Anteil an allen Walkern,
+/// die zensierten eingeschlossen
+fn time_histogram_plot(ui: &mut egui::Ui, sorted_times: &[usize], total: usize, step: usize, height: f32)
+{
+    let max = (step as f64).log10();
+    let bin_width = max / HISTOGRAM_BINS as f64;
+    let mut counts = vec![0_usize; HISTOGRAM_BINS];
+    for &t in sorted_times{
+        let bin = ((t.max(1) as f64).log10() / bin_width) as usize;
+        counts[bin.min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    let bars: Vec<Bar> = counts.into_iter()
+        .enumerate()
+        .map(
+            |(i, count)|
+            {
+                Bar::new((i as f64 + 0.5) * bin_width, count as f64 / total as f64)
+                    .width(bin_width)
+            }
+        ).collect();
+
+    Plot::new("first_passage_histogram")
+        .legend(Legend::default())
+        .height(height)
+        .x_axis_formatter(|v, _| log_axis_label(v))
+        .label_formatter(|_, p| format!("t = {}\nAnteil = {:.3}", log_axis_label(p.x), p.y))
+        .show(
+            ui,
+            |plot_ui|
+            {
+                plot_ui.bar_chart(BarChart::new(bars).name("Walker"));
+            }
+        );
+}
+
+/// Die Überlebenswahrscheinlichkeit S(t) auf einer logarithmischen Zeitachse.
+/// Für die Rückkehr des normalen Random Walks in 2D gilt S(t) ≈ π / ln(t):
+/// Jeder Walker kommt irgendwann zurück (Pólya), aber sehr langsam
+fn survival_plot(
+    ui: &mut egui::Ui,
+    sorted_times: &[usize],
+    total: usize,
+    step: usize,
+    height: f32,
+    show_polya: bool
+)
+{
+    let max = (step as f64).log10();
+    let survival: Vec<PlotPoint> = (0..=200)
+        .map(
+            |i|
+            {
+                let log_t = max * i as f64 / 200.0;
+                let t = 10_f64.powf(log_t);
+                let arrived = sorted_times.partition_point(|&time| time as f64 <= t);
+                PlotPoint { x: log_t, y: (total - arrived) as f64 / total as f64 }
+            }
+        ).collect();
+    // Erst ab t > e^π ist π / ln(t) kleiner als 1
+    let polya: Vec<PlotPoint> = survival.iter()
+        .map(|p| PlotPoint { x: p.x, y: std::f64::consts::PI / (p.x * std::f64::consts::LN_10) })
+        .filter(|p| p.y < 1.0)
+        .collect();
+
+    Plot::new("first_passage_survival")
+        .legend(Legend::default())
+        .height(height)
+        .include_y(0.0)
+        .include_y(1.0)
+        .x_axis_formatter(|v, _| log_axis_label(v))
+        .label_formatter(|name, p| format!("{name}\nt = {}\nS = {:.3}", log_axis_label(p.x), p.y))
+        .show(
+            ui,
+            |plot_ui|
+            {
+                plot_ui.line(Line::new(PlotPoints::Owned(survival)).name("Walker"));
+                if show_polya {
+                    plot_ui.line(Line::new(PlotPoints::Owned(polya)).name("π / ln(t) (normaler Random Walk)"));
+                }
+            }
+        );
+}
@@ -0,0 +1,430 @@
+use egui::plot::*;
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+
+use crate::ensemble::MAX_WALKER_STEPS_PER_UPDATE;
+use crate::power_law::log_axis_label;
+use crate::random_walker::{RandomSequence, RandomWalker};
+use crate::walk_model::WalkModel;
+
+/// Wie viele Züge jeder Kette am Anfang verworfen werden, bis sie im Gleichgewicht ist
+const BURN_IN: u64 = 2000;
+/// Balken mit weniger Einträgen werden nicht verwendet
+const MIN_COUNTS: u64 = 10;
+/// Breite der Balken des Histogramms von d
+const BIN_WIDTH: f64 = 1.0;
+
+/// Lässt einen Walk aus seinen Zufallszahlen laufen und gibt den Abstand am Ende zurück.
+/// Reichen die Zahlen nicht, werden neue aus `rng` angehängt.
+/// None, wenn die Schrittregel ungültig ist
+pub fn replay_distance(
+    model: &mut WalkModel,
+    numbers: &mut Vec<f64>,
+    steps: usize,
+    rng: &mut Pcg64
+) -> Option<f64>
+{
+    let mut walker = RandomWalker::with_capacity_and_rng(Pcg64::from_rng(&mut *rng).unwrap(), steps);
+    walker.replay = Some(RandomSequence::new(std::mem::take(numbers)));
+    let done = model.step_walkers(std::slice::from_mut(&mut walker), steps as u64);
+    *numbers = walker.replay.take().unwrap().numbers;
+    let (x, y) = (walker.ort.x as f64, walker.ort.y as f64);
+    (done == steps as u64).then(|| (x * x + y * y).sqrt())
+}
+
+/// Ein Vorschlag für einen neuen Walk: `mutations` zufällig ausgewählte
+/// Zufallszahlen werden neu gezogen. Der Vorschlag ist symmetrisch,
+/// der Rückweg ist also genauso wahrscheinlich wie der Hinweg
+pub fn mutate(numbers: &[f64], mutations: usize, rng: &mut Pcg64) -> Vec<f64>
+{
+    let mut proposal = numbers.to_vec();
+    if proposal.is_empty() {
+        return proposal;
+    }
+    for _ in 0..mutations{
+        let i = rng.gen_range(0..proposal.len());
+        proposal[i] = rng.gen();
+    }
+    proposal
+}
+
+/// log10 der Wahrscheinlichkeit, dass der normale Random Walk nach `steps` Schritten
+/// einen Abstand im Balken um d hat. Für große n ist der Abstand Rayleigh-verteilt
+/// mit σ² = n/2 - die Gauß-Näherung für die Position
+pub fn rayleigh_log10(d: f64, steps: usize, bin_width: f64) -> f64
+{
+    let sigma2 = steps as f64 / 2.0;
+    ((d / sigma2 * bin_width).ln() - d * d / (2.0 * sigma2)) / std::f64::consts::LN_10
+}
+
+/// Eine Markow-Kette im Raum der Trajektorien bei der Temperatur T.
+///
+/// Die Walks werden mit Gewicht exp(-d / T) gezogen: Bei negativem T sind
+/// große Abstände d bevorzugt, bei positivem T kleine. Ein Zug ändert einige
+/// der Zufallszahlen des Walks und wird mit der Metropolis-Regel angenommen.
+/// Bei T = ∞ wird jedes Mal ein völlig neuer Walk gezogen (einfache Stichprobe)
+#[derive(Debug, Clone)]
+pub struct Chain{
+    pub temperature: f64,
+    rng: Pcg64,
+    numbers: Vec<f64>,
+    distance: f64,
+    pub proposed: u64,
+    pub accepted: u64,
+    /// Pro Balken: Anzahl und Summe der Gewichte exp((d - Mitte) / T)
+    counts: Vec<u64>,
+    weights: Vec<f64>,
+    samples: u64
+}
+
+impl Chain{
+    fn new(temperature: f64, seed: u64) -> Self
+    {
+        Self{
+            temperature,
+            rng: Pcg64::seed_from_u64(seed),
+            numbers: Vec::new(),
+            distance: f64::NAN,
+            proposed: 0,
+            accepted: 0,
+            counts: Vec::new(),
+            weights: Vec::new(),
+            samples: 0
+        }
+    }
+
+    fn is_simple_sampling(&self) -> bool
+    {
+        self.temperature.is_infinite()
+    }
+
+    /// Ein Metropolis-Zug. Gibt false zurück, wenn die Schrittregel ungültig ist
+    fn sweep(&mut self, model: &mut WalkModel, steps: usize, mutations: usize) -> bool
+    {
+        if self.distance.is_nan() {
+            let Some(distance) = replay_distance(model, &mut self.numbers, steps, &mut self.rng) else {
+                return false;
+            };
+            self.distance = distance;
+        }
+        let mut proposal = if self.is_simple_sampling() {
+            Vec::new()
+        } else {
+            mutate(&self.numbers, mutations, &mut self.rng)
+        };
+        let Some(distance) = replay_distance(model, &mut proposal, steps, &mut self.rng) else {
+            return false;
+        };
+        self.proposed += 1;
+        let acceptance = (-(distance - self.distance) / self.temperature).exp();
+        if self.is_simple_sampling() || self.rng.gen::<f64>() < acceptance {
+            self.accepted += 1;
+            self.numbers = proposal;
+            self.distance = distance;
+        }
+        if self.proposed > BURN_IN || self.is_simple_sampling() {
+            self.record();
+        }
+        true
+    }
+
+    fn record(&mut self)
+    {
+        let bin = (self.distance / BIN_WIDTH) as usize;
+        if bin >= self.counts.len() {
+            self.counts.resize(bin + 1, 0);
+            self.weights.resize(bin + 1, 0.0);
+        }
+        let center = (bin as f64 + 0.5) * BIN_WIDTH;
+        self.counts[bin] += 1;
+        self.weights[bin] += ((self.distance - center) / self.temperature).exp();
+        self.samples += 1;
+    }
+
+    /// ln P(d) + ln Z(T) für jeden Balken mit genug Einträgen.
+    /// Da P_T(d) ∝ P(d) · exp(-d / T), ist P(d) ∝ P_T(d) · exp(d / T)
+    fn unnormalized_log_probability(&self) -> Vec<Option<f64>>
+    {
+        self.counts.iter()
+            .zip(&self.weights)
+            .enumerate()
+            .map(
+                |(bin, (&count, &weight))|
+                {
+                    let center = (bin as f64 + 0.5) * BIN_WIDTH;
+                    (count >= MIN_COUNTS)
+                        .then(|| (weight / self.samples as f64).ln() + center / self.temperature)
+                }
+            ).collect()
+    }
+}
+
+fn temperature_label(temperature: f64) -> String
+{
+    if temperature.is_infinite() {
+        "T = ∞".to_owned()
+    } else {
+        format!("T = {temperature}")
+    }
+}
+
+/// Setzt die Stücke der einzelnen Temperaturen zu einer Verteilung zusammen.
+///
+/// Die Stücke sind nur bis auf einen Faktor Z(T) bekannt. Er wird so gewählt,
+/// dass jedes Stück im Überlapp zu den bisherigen passt. Gibt ln P(d) für jeden
+/// Balken zurück, normiert auf 1, und die verschobenen Stücke jeder Kette
+pub fn stitch(chains: &[Chain]) -> (Vec<Option<f64>>, Vec<Vec<Option<f64>>>)
+{
+    let mut order: Vec<usize> = (0..chains.len()).collect();
+    // Von T = ∞ aus zu immer kleineren |T|
+    order.sort_by(|&a, &b| chains[b].temperature.abs().total_cmp(&chains[a].temperature.abs()));
+
+    let bins = chains.iter().map(|chain| chain.counts.len()).max().unwrap_or(0);
+    let mut combined: Vec<Option<f64>> = vec![None; bins];
+    let mut combined_counts = vec![0_u64; bins];
+    let mut pieces = vec![Vec::new(); chains.len()];
+    for i in order{
+        let chain = &chains[i];
+        let piece = chain.unnormalized_log_probability();
+        let overlap: Vec<f64> = piece.iter()
+            .zip(&combined)
+            .filter_map(|(p, c)| Some((*c)? - (*p)?))
+            .collect();
+        let shift = if combined.iter().all(Option::is_none) {
+            0.0
+        } else if overlap.is_empty() {
+            // Kein Überlapp: Das Stück lässt sich nicht einordnen
+            continue;
+        } else {
+            overlap.iter().sum::<f64>() / overlap.len() as f64
+        };
+        let piece: Vec<Option<f64>> = piece.into_iter()
+            .map(|p| p.map(|p| p + shift))
+            .collect();
+        for (bin, p) in piece.iter().enumerate(){
+            let Some(p) = p else {
+                continue;
+            };
+            let count = chain.counts[bin];
+            combined[bin] = Some(
+                match combined[bin]{
+                    // Mittelwert, gewichtet mit der Anzahl der Einträge
+                    Some(c) => {
+                        let old = combined_counts[bin] as f64;
+                        (c * old + p * count as f64) / (old + count as f64)
+                    },
+                    None => *p
+                }
+            );
+            combined_counts[bin] += count;
+        }
+        pieces[i] = piece;
+    }
+
+    // Normieren, mit log-sum-exp gegen Unterlauf
+    let max = combined.iter().flatten().copied().fold(f64::NEG_INFINITY, f64::max);
+    if max.is_finite() {
+        let norm = max + combined.iter()
+            .flatten()
+            .map(|p| (p - max).exp())
+            .sum::<f64>()
+            .ln();
+        for p in combined.iter_mut().chain(pieces.iter_mut().flatten()).flatten(){
+            *p -= norm;
+        }
+    }
+    (combined, pieces)
+}
+
+/// Sampling seltener Walks: Wie wahrscheinlich ist ein Abstand d nach n Schritten,
+/// auch wenn die Wahrscheinlichkeit nur 10^-50 ist?
+#[derive(Debug, Clone)]
+pub struct LargeDeviation{
+    pub steps: usize,
+    pub temperatures: Vec<f64>,
+    pub simple_sampling: bool,
+    /// Wie viele Zufallszahlen ein Zug ändert
+    pub mutations: usize,
+    pub running: bool,
+    model: Option<WalkModel>,
+    pub chains: Vec<Chain>,
+    invalid: bool
+}
+
+impl Default for LargeDeviation{
+    fn default() -> Self {
+        Self{
+            steps: 100,
+            temperatures: vec![-10.0, -5.0, -3.0, -2.0, -1.5, -1.2, -1.0, -0.85, -0.7, -0.6, -0.5],
+            simple_sampling: true,
+            mutations: 2,
+            running: false,
+            model: None,
+            chains: Vec::new(),
+            invalid: false
+        }
+    }
+}
+
+impl LargeDeviation{
+    /// Startet neue Ketten für alle Temperaturen mit einer Kopie des Modells
+    pub fn start(&mut self, model: &WalkModel, seed: u64)
+    {
+        let infinite = self.simple_sampling.then_some(f64::INFINITY);
+        self.chains = infinite.into_iter()
+            .chain(self.temperatures.iter().copied().filter(|t| *t != 0.0))
+            .enumerate()
+            .map(|(i, t)| Chain::new(t, seed.wrapping_add(i as u64)))
+            .collect();
+        self.model = Some(model.clone());
+        self.running = true;
+        self.invalid = false;
+    }
+
+    pub fn is_busy(&self) -> bool
+    {
+        self.running && self.model.is_some() && !self.chains.is_empty()
+    }
+
+    /// Macht ein paar Züge in jeder Kette
+    pub fn run_pending(&mut self)
+    {
+        let Some(model) = self.model.as_mut() else {
+            return;
+        };
+        if !self.running || self.chains.is_empty() {
+            return;
+        }
+        let rounds = (MAX_WALKER_STEPS_PER_UPDATE / (self.steps * self.chains.len())).max(1);
+        for _ in 0..rounds{
+            for chain in self.chains.iter_mut(){
+                if !chain.sweep(model, self.steps, self.mutations) {
+                    self.running = false;
+                    self.invalid = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &WalkModel, seed: u64)
+    {
+        ui.horizontal_wrapped(
+            |ui|
+            {
+                ui.label("Schritte n");
+                ui.add_enabled(!self.running, egui::DragValue::new(&mut self.steps).clamp_range(1..=2000));
+                ui.label("Änderungen pro Zug");
+                ui.add(egui::DragValue::new(&mut self.mutations).clamp_range(1..=100))
+                    .on_hover_text("Wie viele Zufallszahlen des Walks bei einem Zug neu gezogen werden. Weniger Änderungen werden öfter angenommen");
+                ui.checkbox(&mut self.simple_sampling, "T = ∞")
+                    .on_hover_text("Eine Kette, die einfach neue, unabhängige Walks zieht. Sie legt die Normierung fest");
+                if ui.add_enabled(!self.running, egui::Button::new("Starten")).clicked(){
+                    self.start(model, seed);
+                }
+                if self.running {
+                    ui.spinner();
+                    if ui.button("Pause").clicked(){
+                        self.running = false;
+                    }
+                } else if !self.chains.is_empty() && ui.button("Weiter").clicked(){
+                    self.running = true;
+                }
+            }
+        );
+        ui.collapsing(
+            "Temperaturen",
+            |ui|
+            {
+                ui.label("Negative Temperaturen bevorzugen große Abstände, positive kleine. Je näher an 0, desto stärker. Benachbarte Temperaturen müssen sich überlappen");
+                let mut remove = None;
+                ui.horizontal_wrapped(
+                    |ui|
+                    {
+                        for (i, t) in self.temperatures.iter_mut().enumerate(){
+                            ui.add(egui::DragValue::new(t).speed(0.01).prefix("T = "));
+                            if ui.small_button("x").clicked(){
+                                remove = Some(i);
+                            }
+                        }
+                        if ui.button("+").clicked(){
+                            let last = self.temperatures.last().copied().unwrap_or(-1.0);
+                            self.temperatures.push(last * 0.8);
+                        }
+                    }
+                );
+                if let Some(i) = remove {
+                    self.temperatures.remove(i);
+                }
+                ui.label("Änderungen gelten beim nächsten Start");
+            }
+        );
+        if self.invalid {
+            ui.colored_label(ui.visuals().error_fg_color, "Die Schrittregel ist ungültig");
+        }
+        if self.chains.is_empty() {
+            ui.label("Noch keine Daten");
+            return;
+        }
+        ui.horizontal_wrapped(
+            |ui|
+            {
+                for chain in &self.chains{
+                    let rate = chain.accepted as f64 / chain.proposed.max(1) as f64;
+                    ui.label(format!("{}: {} Züge, {:.0} % angenommen", temperature_label(chain.temperature), chain.proposed, 100.0 * rate))
+                        .on_hover_text("Wird zu wenig angenommen, sollte man weniger Zufallszahlen pro Zug ändern");
+                    ui.separator();
+                }
+            }
+        );
+
+        let (combined, pieces) = stitch(&self.chains);
+        let to_points = |log_p: &[Option<f64>]| -> Vec<PlotPoint> {
+            log_p.iter()
+                .enumerate()
+                .filter_map(
+                    |(bin, p)|
+                    {
+                        let d = (bin as f64 + 0.5) * BIN_WIDTH;
+                        p.map(|p| PlotPoint { x: d, y: p / std::f64::consts::LN_10 })
+                    }
+                ).collect()
+        };
+        let smallest = combined.iter().flatten().copied().fold(0.0, f64::min) / std::f64::consts::LN_10;
+        ui.label(format!("Kleinste geschätzte Wahrscheinlichkeit: {}", log_axis_label(smallest)));
+
+        let max_d = combined.len() as f64 * BIN_WIDTH;
+        let rayleigh: Vec<PlotPoint> = (1..=200)
+            .map(|i| max_d * i as f64 / 200.0)
+            .map(|d| PlotPoint { x: d, y: rayleigh_log10(d, self.steps, BIN_WIDTH) })
+            .collect();
+
+        Plot::new("large_deviation_plot")
+            .legend(Legend::default())
+            .y_axis_formatter(|v, _| log_axis_label(v))
+            .label_formatter(|name, p| format!("{name}\nd = {:.1}\nP = {}", p.x, log_axis_label(p.y)))
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    for (chain, piece) in self.chains.iter().zip(&pieces){
+                        plot_ui.line(
+                            Line::new(PlotPoints::Owned(to_points(piece)))
+                                .name(temperature_label(chain.temperature))
+                                .width(1.0)
+                        );
+                    }
+                    plot_ui.points(
+                        Points::new(PlotPoints::Owned(to_points(&combined)))
+                            .name("P(d) zusammengesetzt")
+                            .radius(2.5)
+                    );
+                    plot_ui.line(
+                        Line::new(PlotPoints::Owned(rayleigh))
+                            .name("Rayleigh (normaler Random Walk)")
+                            .style(LineStyle::dashed_loose())
+                    );
+                }
+            );
+    }
+}
@@ -2,4 +2,24 @@
 mod app;
 pub use app::TemplateApp;
 pub mod random_walker;
-pub mod animation;
\ No newline at end of file
+pub mod step_encoding;
+pub mod visited_sites;
+pub mod animation;
+pub mod step_table;
+pub mod step_script;
+pub mod power_law;
+pub mod walk_model;
+pub mod ensemble;
+pub mod coverage;
+pub mod first_passage;
+pub mod shape;
+pub mod observable;
+pub mod arcsine;
+pub mod winding;
+pub mod runs;
+pub mod comparison;
+pub mod sweep;
+pub mod large_deviation;
+pub mod wang_landau;
+pub mod exact;
+pub mod analytical;
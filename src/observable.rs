@@ -0,0 +1,412 @@
+use egui::{plot::*, Color32};
+use rayon::prelude::*;
+
+use crate::random_walker::{AverageDistance, RandomWalker, StepRecord, Welford};
+
+/// Eine Messgröße, die man für jeden Walker nach jedem Schritt ausrechnen kann.
+///
+/// Um eine neue Messgröße hinzuzufügen, muss man nur dieses Trait implementieren
+/// und die Messgröße in [`default_observables`] eintragen.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::observable::Observable;
+/// use girls_day::random_walker::{RandomWalker, StepRecord};
+///
+/// struct ManhattanDistance;
+///
+/// impl Observable for ManhattanDistance{
+///     fn name(&self) -> &'static str { "Manhattan-Abstand" }
+///
+///     fn value(&self, record: &StepRecord, _previous: Option<f64>) -> f64 {
+///         let pos = &record.position;
+///         (pos.x.abs() + pos.y.abs()) as f64
+///     }
+/// }
+///
+/// let mut walker = RandomWalker::new(1);
+/// walker.random_step();
+/// walker.random_step();
+/// let values: Vec<f64> = walker.history.records(0)
+///     .map(|record| ManhattanDistance.value(&record, None))
+///     .collect();
+/// assert_eq!(values, [0.0, 1.0]);
+/// ```
+pub trait Observable: Send + Sync{
+    fn name(&self) -> &'static str;
+
+    fn hover_text(&self) -> &'static str
+    {
+        ""
+    }
+
+    /// Der Wert für den Eintrag `record` der History, siehe [`crate::random_walker::History::records`].
+    /// `previous` ist der Wert des gleichen Walkers beim Schritt davor,
+    /// damit lassen sich z.B. Maxima ohne erneutes Durchsuchen berechnen
+    fn value(&self, record: &StepRecord, previous: Option<f64>) -> f64;
+}
+
+pub struct XCoordinate;
+
+impl Observable for XCoordinate{
+    fn name(&self) -> &'static str
+    {
+        "x-Koordinate"
+    }
+
+    fn value(&self, record: &StepRecord, _previous: Option<f64>) -> f64
+    {
+        record.position.x as f64
+    }
+}
+
+pub struct YCoordinate;
+
+impl Observable for YCoordinate{
+    fn name(&self) -> &'static str
+    {
+        "y-Koordinate"
+    }
+
+    fn value(&self, record: &StepRecord, _previous: Option<f64>) -> f64
+    {
+        record.position.y as f64
+    }
+}
+
+pub struct Distance;
+
+impl Observable for Distance{
+    fn name(&self) -> &'static str
+    {
+        "Abstand"
+    }
+
+    fn value(&self, record: &StepRecord, _previous: Option<f64>) -> f64
+    {
+        record.distance_from_origin as f64
+    }
+}
+
+pub struct MaxDistance;
+
+impl Observable for MaxDistance{
+    fn name(&self) -> &'static str
+    {
+        "Maximaler Abstand"
+    }
+
+    fn hover_text(&self) -> &'static str
+    {
+        "Der größte Abstand vom Ursprung, den der Walker bis zu diesem Schritt hatte"
+    }
+
+    fn value(&self, record: &StepRecord, previous: Option<f64>) -> f64
+    {
+        let distance = record.distance_from_origin as f64;
+        previous.map_or(distance, |max| max.max(distance))
+    }
+}
+
+pub struct Angle;
+
+impl Observable for Angle{
+    fn name(&self) -> &'static str
+    {
+        "Winkel"
+    }
+
+    fn hover_text(&self) -> &'static str
+    {
+        "Der Winkel der Position zur x-Achse im Bogenmaß, zwischen -π und π. Im Ursprung ist er 0"
+    }
+
+    fn value(&self, record: &StepRecord, _previous: Option<f64>) -> f64
+    {
+        let pos = &record.position;
+        (pos.y as f64).atan2(pos.x as f64)
+    }
+}
+
+pub struct WindingAngle;
+
+impl Observable for WindingAngle{
+    fn name(&self) -> &'static str
+    {
+        "Windungswinkel"
+    }
+
+    fn hover_text(&self) -> &'static str
+    {
+        "Wie weit sich der Walker bisher um den Ursprung gedreht hat, im Bogenmaß. Anders als der Winkel springt er nicht von π nach -π"
+    }
+
+    fn value(&self, record: &StepRecord, _previous: Option<f64>) -> f64
+    {
+        record.winding_angle as f64
+    }
+}
+
+/// Alle Messgrößen, die im Diagramm zur Auswahl stehen
+pub fn default_observables() -> Vec<Box<dyn Observable>>
+{
+    vec![
+        Box::new(XCoordinate),
+        Box::new(YCoordinate),
+        Box::new(Distance),
+        Box::new(MaxDistance),
+        Box::new(Angle),
+        Box::new(WindingAngle)
+    ]
+}
+
+/// Zusammenfassung über alle Walker bei einem Schritt
+#[derive(Debug, Clone, Default)]
+pub struct StepSummary{
+    pub stats: Welford,
+    /// 10%-Quantil, Median und 90%-Quantil
+    pub quantiles: [f64; 3]
+}
+
+impl StepSummary{
+    pub const QUANTILES: [f64; 3] = [0.1, 0.5, 0.9];
+
+    pub fn from_values(mut values: Vec<f64>) -> Self
+    {
+        let mut stats = Welford::default();
+        values.iter().for_each(|v| stats.push(*v));
+        values.sort_unstable_by(f64::total_cmp);
+        let quantiles = Self::QUANTILES.map(|p| quantile(&values, p));
+        Self { stats, quantiles }
+    }
+}
+
+/// Quantil einer sortierten Liste (nächster Rang)
+pub fn quantile(sorted: &[f64], p: f64) -> f64
+{
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index]
+}
+
+/// Wie viele Schritte pro Bild höchstens nachgerechnet werden
+const MAX_STEPS_PER_UPDATE: usize = 20000;
+
+struct Entry{
+    observable: Box<dyn Observable>,
+    enabled: bool,
+    /// Der letzte Wert jedes Walkers
+    last_values: Vec<Option<f64>>,
+    summaries: Vec<StepSummary>
+}
+
+impl Entry{
+    fn reset(&mut self)
+    {
+        self.last_values.clear();
+        self.summaries.clear();
+    }
+
+    /// Rechnet die Messgröße für die Schritte aus, die noch fehlen - aber höchstens
+    /// für [`MAX_STEPS_PER_UPDATE`] Schritte, damit die Oberfläche nicht hängt
+    fn update(&mut self, walkers: &[RandomWalker])
+    {
+        let history_len = walkers[0].history.len();
+        if self.last_values.len() != walkers.len() || self.summaries.len() > history_len {
+            self.reset();
+            self.last_values = vec![None; walkers.len()];
+        }
+        let start = self.summaries.len();
+        let len = history_len.min(start + MAX_STEPS_PER_UPDATE);
+        if start == len {
+            return;
+        }
+        let observable = &self.observable;
+        // values[walker][schritt - start]
+        let values: Vec<Vec<f64>> = walkers.par_iter()
+            .zip(self.last_values.par_iter_mut())
+            .map(
+                |(walker, last)|
+                {
+                    walker.history.records(start)
+                        .take(len - start)
+                        .map(
+                            |record|
+                            {
+                                let value = observable.value(&record, *last);
+                                *last = Some(value);
+                                value
+                            }
+                        ).collect()
+                }
+            ).collect();
+        let new_summaries: Vec<StepSummary> = (0..len - start)
+            .into_par_iter()
+            .map(|i| StepSummary::from_values(values.iter().map(|v| v[i]).collect()))
+            .collect();
+        self.summaries.extend(new_summaries);
+    }
+}
+
+/// Ein Diagramm, in dem man beliebige Messgrößen per Häkchen auswählen kann.
+/// Es werden nur die ausgewählten Messgrößen berechnet - wird eine neu ausgewählt,
+/// wird sie für alle bisherigen Schritte nachgerechnet
+pub struct ObservablePanel{
+    entries: Vec<Entry>,
+    pub show_quantiles: bool,
+    pub show_error_band: bool
+}
+
+impl Default for ObservablePanel{
+    fn default() -> Self {
+        Self::new(default_observables())
+    }
+}
+
+impl ObservablePanel{
+    pub fn new(observables: Vec<Box<dyn Observable>>) -> Self
+    {
+        let entries = observables.into_iter()
+            .enumerate()
+            .map(
+                |(i, observable)|
+                Entry{
+                    observable,
+                    enabled: i == 0,
+                    last_values: Vec::new(),
+                    summaries: Vec::new()
+                }
+            ).collect();
+        Self { entries, show_quantiles: true, show_error_band: true }
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.entries.iter_mut().for_each(Entry::reset);
+    }
+
+    /// Die Zusammenfassungen aller bisherigen Schritte, falls die Messgröße ausgewählt ist
+    pub fn summaries(&self, name: &str) -> Option<&[StepSummary]>
+    {
+        self.entries.iter()
+            .find(|entry| entry.enabled && entry.observable.name() == name)
+            .map(|entry| entry.summaries.as_slice())
+    }
+
+    pub fn update(&mut self, walkers: &[RandomWalker])
+    {
+        self.entries.iter_mut()
+            .filter(|entry| entry.enabled)
+            .for_each(|entry| entry.update(walkers));
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, walkers: &[RandomWalker])
+    {
+        ui.horizontal_wrapped(
+            |ui|
+            {
+                for entry in self.entries.iter_mut(){
+                    let checkbox = ui.checkbox(&mut entry.enabled, entry.observable.name());
+                    let hover_text = entry.observable.hover_text();
+                    if !hover_text.is_empty() {
+                        checkbox.on_hover_text(hover_text);
+                    }
+                }
+                ui.separator();
+                ui.checkbox(&mut self.show_error_band, "± Standardfehler");
+                ui.checkbox(&mut self.show_quantiles, "Quantile")
+                    .on_hover_text("Median (gestrichelt) sowie 10%- und 90%-Quantil (gepunktet)");
+            }
+        );
+        self.update(walkers);
+        let history_len = walkers[0].history.len();
+        if let Some(behind) = self.entries.iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| history_len - entry.summaries.len())
+            .max()
+            .filter(|behind| *behind > 0)
+        {
+            ui.horizontal(
+                |ui|
+                {
+                    ui.spinner();
+                    ui.label(format!("Noch {behind} Schritte werden nachgerechnet"));
+                }
+            );
+        }
+
+        let show_quantiles = self.show_quantiles;
+        let show_error_band = self.show_error_band;
+        Plot::new("observable_plot")
+            .legend(Legend::default())
+            .include_x(0.0)
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    for (i, entry) in self.entries.iter().enumerate().filter(|(_, entry)| entry.enabled){
+                        let color = PALETTE[i % PALETTE.len()];
+                        let name = entry.observable.name();
+                        // Höchstens etwa 2000 Punkte pro Linie
+                        let stride = (entry.summaries.len() / 2000).max(1);
+                        let points = |f: &dyn Fn(&StepSummary) -> f64| -> Vec<PlotPoint> {
+                            entry.summaries.iter()
+                                .enumerate()
+                                .step_by(stride)
+                                .map(|(x, summary)| PlotPoint { x: x as f64, y: f(summary) })
+                                .collect()
+                        };
+
+                        if show_error_band {
+                            let stats: Vec<Welford> = entry.summaries.iter()
+                                .map(|summary| summary.stats)
+                                .collect();
+                            let band_stride = (stats.len() / 500).max(1);
+                            for quad in AverageDistance::error_band(&stats, 1, band_stride){
+                                let corners = quad.iter()
+                                    .map(|[x, y]| PlotPoint { x: *x, y: *y })
+                                    .collect();
+                                plot_ui.polygon(
+                                    Polygon::new(PlotPoints::Owned(corners))
+                                        .name(name)
+                                        .color(color)
+                                        .fill_alpha(0.3)
+                                        .width(0.0)
+                                );
+                            }
+                        }
+                        plot_ui.line(
+                            Line::new(PlotPoints::Owned(points(&|s| s.stats.mean())))
+                                .name(name)
+                                .color(color)
+                        );
+                        if show_quantiles {
+                            for (q, style) in [
+                                (0, LineStyle::dotted_dense()),
+                                (1, LineStyle::dashed_loose()),
+                                (2, LineStyle::dotted_dense())
+                            ]{
+                                plot_ui.line(
+                                    Line::new(PlotPoints::Owned(points(&|s| s.quantiles[q])))
+                                        .name(name)
+                                        .color(color)
+                                        .style(style)
+                                );
+                            }
+                        }
+                    }
+                }
+            );
+    }
+}
+
+const PALETTE: [Color32; 6] = [
+    Color32::from_rgb(0, 150, 255),
+    Color32::from_rgb(254, 42, 42),
+    Color32::from_rgb(80, 200, 120),
+    Color32::from_rgb(255, 170, 0),
+    Color32::from_rgb(190, 90, 255),
+    Color32::from_rgb(0, 210, 210)
+];
@@ -0,0 +1,84 @@
+use egui::plot::PlotPoint;
+
+/// Ergebnis einer Anpassung y = a · t^b
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerLawFit{
+    pub prefactor: f64,
+    pub exponent: f64
+}
+
+impl PowerLawFit{
+    pub fn eval(&self, t: f64) -> f64
+    {
+        self.prefactor * t.powf(self.exponent)
+    }
+}
+
+/// Passt y = a · t^b mit der Methode der kleinsten Quadrate an.
+///
+/// Dafür wird ausgenutzt, dass ln(y) = ln(a) + b · ln(t) eine Gerade ist.
+/// Punkte mit t <= 0 oder y <= 0 werden ignoriert.
+/// Gibt None zurück, wenn es weniger als 2 verschiedene t gibt.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::power_law::fit_power_law;
+/// use egui::plot::PlotPoint;
+/// let points: Vec<_> = (1..100)
+///     .map(|t| PlotPoint::new(t as f64, 3.0 * (t as f64).sqrt()))
+///     .collect();
+/// let fit = fit_power_law(&points).unwrap();
+/// assert!((fit.exponent - 0.5).abs() < 1e-10);
+/// assert!((fit.prefactor - 3.0).abs() < 1e-10);
+/// ```
+pub fn fit_power_law(points: &[PlotPoint]) -> Option<PowerLawFit>
+{
+    let mut n = 0.0;
+    let mut sum_x = 0.0;
+    let mut sum_y = 0.0;
+    let mut sum_xx = 0.0;
+    let mut sum_xy = 0.0;
+    for p in points.iter().filter(|p| p.x > 0.0 && p.y > 0.0){
+        let x = p.x.ln();
+        let y = p.y.ln();
+        n += 1.0;
+        sum_x += x;
+        sum_y += y;
+        sum_xx += x * x;
+        sum_xy += x * y;
+    }
+    let denominator = n * sum_xx - sum_x * sum_x;
+    if n < 2.0 || denominator.abs() < f64::EPSILON {
+        return None;
+    }
+    let exponent = (n * sum_xy - sum_x * sum_y) / denominator;
+    let ln_prefactor = (sum_y - exponent * sum_x) / n;
+    Some(
+        PowerLawFit{
+            prefactor: ln_prefactor.exp(),
+            exponent
+        }
+    )
+}
+
+/// Rechnet die Punkte für eine doppelt-logarithmische Darstellung um: (log10 t, log10 y).
+/// Punkte die sich nicht logarithmieren lassen werden weggelassen
+pub fn log_log(points: impl IntoIterator<Item = PlotPoint>) -> Vec<PlotPoint>
+{
+    points.into_iter()
+        .filter(|p| p.x > 0.0 && p.y > 0.0)
+        .map(|p| PlotPoint { x: p.x.log10(), y: p.y.log10() })
+        .collect()
+}
+
+/// Beschriftung einer logarithmischen Achse: Aus log10(t) wird wieder t
+pub fn log_axis_label(value: f64) -> String
+{
+    let original = 10_f64.powf(value);
+    if (1e-3..1e5).contains(&original) {
+        let rounded = format!("{original:.3}");
+        rounded.trim_end_matches('0').trim_end_matches('.').to_owned()
+    } else {
+        format!("{original:.1e}")
+    }
+}
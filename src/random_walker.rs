@@ -1,8 +1,20 @@
 
+use std::collections::HashMap;
 use egui::plot::PlotPoint;
 use rand_pcg::Pcg64;
 use rand::prelude::*;
+use rayon::prelude::*;
 
+use crate::shape::{Gyration, Shape};
+use crate::step_encoding::StepEncoding;
+use crate::arcsine::HalfPlane;
+use crate::winding::Winding;
+use crate::visited_sites::VisitedSites;
+
+
+/// Die vier möglichen Schritte auf dem Gitter,
+/// in der Reihenfolge rechts, links, oben, unten
+pub const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
 
 /// Ein "Struct" - quasi eine Sammlung von Variablen
 /// Hier von 2 ganzen Zahlen die als x und y koordinate dienen
@@ -14,10 +26,115 @@ pub struct Position{
     pub y: i32,
 }
 
-#[derive(Debug, Clone, Default)]
+/// Nach so vielen Einträgen speichert die [`History`] jeweils einen Zwischenstand
+const CHECKPOINT_INTERVAL: usize = 1024;
+/// Wie viele Messwerte aller Walker zusammen [`AverageDistance::update_on_step_of_walkers`]
+/// höchstens auf einmal aus den Histories liest
+pub const MAX_RECORDS_PER_CHUNK: usize = 1 << 18;
+/// Ab so vielen Einträgen behält [`AverageDistance`] bei Walkern ohne Spur nur noch
+/// jeden zweiten, siehe [`AverageDistance::steps_per_entry`]
+const MAX_AVERAGE_ENTRIES: usize = 1 << 16;
+
+/// Der Abstand vom Ursprung, so wie er in den Plots verwendet wird.
+/// Das Quadrat wird in f64 gebildet, weil x² + y² schon ab |x| > 46340 nicht mehr in i32 passt
+///
+/// ```
+/// use girls_day::random_walker::{distance_from_origin, Position};
+/// let far = Position { x: 100_000, y: 0 };
+/// assert_eq!(distance_from_origin(&far), 100_000.0);
+/// ```
+pub fn distance_from_origin(pos: &Position) -> f32
+{
+    let (x, y) = (pos.x as f64, pos.y as f64);
+    (x * x + y * y).sqrt() as f32
+}
+
+/// Zwischenstand der History vor dem Eintrag `i · CHECKPOINT_INTERVAL`
+#[derive(Debug, Clone)]
+struct Checkpoint{
+    position: Position,
+    /// Die Summen für den Gyrationsradius aller Einträge davor
+    gyration: Gyration,
+    /// Der Windungswinkel vor diesem Eintrag
+    winding: Winding,
+    /// S(t) vor diesem Eintrag
+    distinct_sites: u32
+}
+
+/// Die bisherigen Positionen eines Walkers.
+///
+/// Damit auch lange Walks vieler Walker in den Speicher passen, wird nicht jede
+/// Position gespeichert, sondern nur der Schritt dorthin (meist 2 Bit, siehe
+/// [`StepEncoding`]). Alle 1024 Einträge gibt es einen Zwischenstand, von dem aus die
+/// Positionen wieder zusammengezählt werden. Für S(t) gibt es ein Bit pro Eintrag, ob
+/// dort ein neues Feld dazukam, die besuchten Felder selbst stehen in [`VisitedSites`].
+/// Für die konvexe Hülle werden nur die Einträge gespeichert, bei denen sie wächst.
+///
+/// Bei einem normalen Random Walk mit 500 000 Schritten sind das pro Schritt etwa
+/// 0,25 Byte für den Schritt, 0,125 Byte für S(t), 0,12 Byte für die besuchten Felder,
+/// 0,15 Byte für die Hülle und 0,07 Byte für die Zwischenstände, zusammen also
+/// ungefähr 0,7 Byte oder 350 kB pro Walker.
+///
+/// Der Eintrag t ist die Position nach t Schritten.
+/// Alle Werte eines Eintrags liefert [`History::records`].
+///
+/// Eine History ohne Spur (siehe [`History::without_trajectory`]) merkt sich nur die
+/// Messwerte der Einträge seit dem letzten [`History::forget_records`], außerdem
+/// Gyrationsradius, Hülle, Arkussinus und Windungen. Die besuchten Felder zählt sie nicht.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::random_walker::*;
+/// let mut walker = RandomWalker::new(5);
+/// for _ in 0..5000 {
+///     walker.random_step();
+/// }
+/// let history = &walker.history;
+/// assert_eq!(history.len(), 5000);
+/// assert_eq!(history.position(0), Position { x: 0, y: 0 });
+/// // Schritt für Schritt zusammengezählt ergibt sich die gleiche Position wie direkt
+/// let records: Vec<StepRecord> = history.records(0).collect();
+/// for t in [1, 63, 64, 1023, 1024, 1025, 4999] {
+///     assert_eq!(records[t].position, history.position(t));
+///     assert_eq!(records[t].distinct_sites, history.distinct_sites(t));
+/// }
+/// let later: Vec<StepRecord> = history.records(3000).collect();
+/// assert_eq!(later[0].radius_of_gyration, records[3000].radius_of_gyration);
+/// assert_eq!(later[0].hull_area, records[3000].hull_area);
+/// assert_eq!(later[0].winding_angle, records[3000].winding_angle);
+/// assert_eq!(records[4999].winding_angle, history.winding.angle() as f32);
+/// assert_eq!(history.distinct_sites(4999) as usize, history.visited.len());
+/// ```
+#[derive(Debug, Clone)]
 pub struct History{
-    pub vec: Vec<Position>,
-    pub distance_from_origin: Vec<f32>
+    len: usize,
+    /// Ob alle Positionen gespeichert werden
+    trajectory: bool,
+    steps: StepEncoding,
+    checkpoints: Vec<Checkpoint>,
+    last: Option<Position>,
+    /// Alle Felder, auf denen der Walker schon war
+    pub visited: VisitedSites,
+    /// Ein Bit pro Eintrag, gesetzt wenn dort ein neues Feld dazukam.
+    /// S(t) ist die Anzahl davon bis t
+    new_sites: Vec<u64>,
+    /// Die Einträge, bei denen die konvexe Hülle größer wurde, mit ihrer neuen Fläche
+    hull_growth: Vec<(u32, f32)>,
+    /// Ohne Spur: die Messwerte ab dem Eintrag `recent_start`
+    recent: Vec<StepRecord>,
+    recent_start: usize,
+    /// Gyrationsradius und konvexe Hülle der bisherigen Positionen
+    pub shape: Shape,
+    /// Zeit mit x > 0 und letzter Besuch der y-Achse, für die Arkussinus-Gesetze
+    pub half_plane: HalfPlane,
+    /// Wie weit sich der Walker bisher um den Ursprung gedreht hat
+    pub winding: Winding
+}
+
+impl Default for History{
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
 }
 
 impl History{
@@ -28,25 +145,390 @@ impl History{
     pub fn with_capacity(capacity: usize) -> Self
     {
         Self{
-            vec: Vec::with_capacity(capacity),
-            distance_from_origin: Vec::with_capacity(capacity)
+            len: 0,
+            trajectory: true,
+            steps: StepEncoding::with_capacity(capacity),
+            checkpoints: Vec::with_capacity(capacity / CHECKPOINT_INTERVAL + 1),
+            last: None,
+            visited: VisitedSites::default(),
+            new_sites: Vec::with_capacity(capacity / 64 + 1),
+            hull_growth: Vec::new(),
+            recent: Vec::new(),
+            recent_start: 0,
+            shape: Shape::default(),
+            half_plane: HalfPlane::default(),
+            winding: Winding::default()
+        }
+    }
+
+    /// Eine History, die keine Spur speichert, für große Ensembles, von denen man
+    /// nur die Mittelwerte braucht. Ihr Speicher wächst nicht mit der Anzahl der Schritte,
+    /// solange nach dem Auslesen [`History::forget_records`] aufgerufen wird
+    pub fn without_trajectory() -> Self
+    {
+        Self{
+            trajectory: false,
+            ..Self::with_capacity(0)
         }
     }
 
+    pub fn keeps_trajectory(&self) -> bool
+    {
+        self.trajectory
+    }
+
+    /// Vergisst die Messwerte der bisherigen Einträge, wenn die History keine Spur speichert
+    pub fn forget_records(&mut self)
+    {
+        self.recent_start = self.len;
+        self.recent.clear();
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.vec.is_empty()
+        self.len == 0
     }
 
     pub fn len(&self) -> usize 
     {
-        self.vec.len()
+        self.len
+    }
+
+    /// Für wie viele Einträge schon Platz reserviert ist
+    pub fn capacity(&self) -> usize
+    {
+        self.steps.capacity()
+    }
+
+    /// Wie viele Bit ein Schritt gerade braucht
+    pub fn bits_per_step(&self) -> u32
+    {
+        self.steps.bits_per_step()
+    }
+
+    pub fn last(&self) -> Option<&Position>
+    {
+        self.last.as_ref()
     }
 
     pub fn push(&mut self, pos: Position)
     {
-        let distance_from_origin = ((pos.x * pos.x + pos.y*pos.y) as f32).sqrt();
-        self.vec.push(pos);
-        self.distance_from_origin.push(distance_from_origin);
+        let index = self.len;
+        if self.trajectory {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                self.checkpoints.push(
+                    Checkpoint{
+                        position: pos.clone(),
+                        gyration: self.shape.gyration,
+                        winding: self.winding,
+                        distinct_sites: self.visited.len() as u32
+                    }
+                );
+            }
+            if let Some(last) = &self.last {
+                self.steps.push((pos.x - last.x, pos.y - last.y));
+            }
+            if index % 64 == 0 {
+                self.new_sites.push(0);
+            }
+            if self.visited.insert(&pos) {
+                *self.new_sites.last_mut().unwrap() |= 1 << (index % 64);
+            }
+        }
+        let hull_area = self.shape.hull.area();
+        self.shape.add(&pos);
+        self.winding.add(&pos);
+        if self.trajectory {
+            if self.shape.hull.area() != hull_area {
+                self.hull_growth.push((index as u32, self.shape.hull.area() as f32));
+            }
+        } else {
+            self.recent.push(
+                StepRecord{
+                    step: index,
+                    distance_from_origin: distance_from_origin(&pos),
+                    position: pos.clone(),
+                    distinct_sites: 0,
+                    radius_of_gyration: self.shape.gyration.radius_of_gyration() as f32,
+                    hull_area: self.shape.hull.area() as f32,
+                    winding_angle: self.winding.angle() as f32
+                }
+            );
+        }
+        self.half_plane.add(&pos);
+        self.last = Some(pos);
+        self.len += 1;
+    }
+
+    /// Merkt sich den jetzigen Stand, damit [`History::truncate`] später wieder bis
+    /// hierhin zurücknehmen kann. Ohne Spur ist die History klein und wird kopiert,
+    /// mit Spur reicht die Länge
+    pub fn mark(&self) -> HistoryMark
+    {
+        HistoryMark{
+            len: self.len,
+            earlier: (!self.trajectory).then(|| self.clone())
+        }
+    }
+
+    /// Nimmt alle Einträge ab `len` zurück. Mit Spur wird die History aus ihren
+    /// ersten `len` Positionen neu aufgebaut, ohne Spur vom Stand `mark` aus,
+    /// der höchstens `len` Einträge haben darf.
+    ///
+    /// ```
+    /// use girls_day::random_walker::*;
+    /// use rand::SeedableRng;
+    /// let streaming = RandomWalker::without_trajectory(rand_pcg::Pcg64::seed_from_u64(2));
+    /// for mut walker in [RandomWalker::new(2), streaming] {
+    ///     for _ in 0..1500 {
+    ///         walker.random_step();
+    ///     }
+    ///     let mark = walker.history.mark();
+    ///     let mut shorter = walker.clone();
+    ///     for _ in 0..700 {
+    ///         walker.random_step();
+    ///     }
+    ///     for _ in 0..300 {
+    ///         shorter.random_step();
+    ///     }
+    ///     let position = walker.history.position(1800);
+    ///     walker.history.truncate(1800, &mark);
+    ///     assert_eq!(walker.history.len(), 1800);
+    ///     assert_eq!(walker.history.last(), shorter.history.last());
+    ///     assert_eq!(walker.history.shape.gyration.radius_of_gyration(), shorter.history.shape.gyration.radius_of_gyration());
+    ///     assert_eq!(walker.history.winding.angle(), shorter.history.winding.angle());
+    ///     assert_eq!(walker.history.visited.len(), shorter.history.visited.len());
+    ///     assert_eq!(position, shorter.ort);
+    /// }
+    /// ```
+    pub fn truncate(&mut self, len: usize, mark: &HistoryMark)
+    {
+        if len >= self.len {
+            return;
+        }
+        let (mut rebuilt, from) = match &mark.earlier {
+            Some(earlier) => {
+                assert!(mark.len <= len, "Ohne Spur lässt sich nur bis zur Markierung zurücknehmen");
+                (earlier.clone(), mark.len)
+            },
+            None => (Self::with_capacity(self.capacity()), 0)
+        };
+        for pos in self.positions(from).take(len - from) {
+            rebuilt.push(pos);
+        }
+        *self = rebuilt;
+    }
+
+    /// Die Positionen ab dem Eintrag `from`. Ohne Spur nur die, deren Messwerte
+    /// noch nicht vergessen wurden
+    pub fn positions(&self, from: usize) -> Positions<'_>
+    {
+        if !self.trajectory {
+            return Positions { history: self, next: from.max(self.recent_start), position: Position { x: 0, y: 0 } };
+        }
+        let checkpoint = from / CHECKPOINT_INTERVAL;
+        let mut positions = Positions{
+            history: self,
+            next: checkpoint * CHECKPOINT_INTERVAL,
+            position: self.checkpoints.get(checkpoint).map(|c| c.position.clone()).unwrap_or(Position { x: 0, y: 0 })
+        };
+        while positions.next < from.min(self.len()) {
+            positions.advance();
+        }
+        positions
+    }
+
+    /// Die Position nach `t` Schritten. Braucht bis zu 1024 Schritte zum Zusammenzählen,
+    /// für viele Positionen hintereinander ist [`History::positions`] schneller
+    pub fn position(&self, t: usize) -> Position
+    {
+        assert!(t < self.len(), "Eintrag {t} gibt es nicht, die History hat {} Einträge", self.len());
+        self.positions(t)
+            .next()
+            .unwrap_or_else(|| panic!("Eintrag {t} wurde schon vergessen, die History speichert keine Spur"))
+    }
+
+    /// S(t): Auf wie vielen verschiedenen Feldern der Walker bis zum Schritt `t` war.
+    /// Ohne Spur immer 0
+    pub fn distinct_sites(&self, t: usize) -> u32
+    {
+        let t = t.min(self.len.saturating_sub(1));
+        let Some(checkpoint) = self.checkpoints.get(t / CHECKPOINT_INTERVAL) else {
+            return 0;
+        };
+        // Vom Zwischenstand aus die neuen Felder bis einschließlich t zählen.
+        // Ein Zwischenstand fängt immer am Anfang eines u64 an
+        let first = t / CHECKPOINT_INTERVAL * CHECKPOINT_INTERVAL / 64;
+        let last = t / 64;
+        let before_last: u32 = self.new_sites[first..last].iter()
+            .map(|bits| bits.count_ones())
+            .sum();
+        let up_to_t = u64::MAX >> (63 - t % 64);
+        checkpoint.distinct_sites + before_last + (self.new_sites[last] & up_to_t).count_ones()
+    }
+
+    /// Ob beim Eintrag `t` ein Feld zum ersten Mal besucht wurde
+    fn is_new_site(&self, t: usize) -> bool
+    {
+        self.new_sites.get(t / 64).map_or(false, |bits| bits & (1 << (t % 64)) != 0)
+    }
+
+    /// Die Fläche der konvexen Hülle der Positionen bis zum Schritt `t`
+    pub fn hull_area(&self, t: usize) -> f32
+    {
+        let changes = self.hull_growth.partition_point(|(i, _)| *i as usize <= t);
+        changes.checked_sub(1).map_or(0.0, |i| self.hull_growth[i].1)
+    }
+
+    /// Alle Messwerte der Einträge ab `from`, Schritt für Schritt
+    pub fn records(&self, from: usize) -> Records<'_>
+    {
+        if !self.trajectory {
+            return Records{
+                positions: self.positions(from),
+                from,
+                gyration: Gyration::default(),
+                winding: Winding::default(),
+                distinct_sites: 0,
+                hull_growth: 0
+            };
+        }
+        let checkpoint = from / CHECKPOINT_INTERVAL;
+        let (gyration, winding, distinct_sites) = self.checkpoints.get(checkpoint)
+            .map(|c| (c.gyration, c.winding, c.distinct_sites))
+            .unwrap_or_default();
+        Records{
+            positions: self.positions(checkpoint * CHECKPOINT_INTERVAL),
+            from,
+            gyration,
+            winding,
+            distinct_sites,
+            hull_growth: self.hull_growth.partition_point(|(i, _)| (*i as usize) < from)
+        }
+    }
+}
+
+/// Ein Stand einer [`History`], siehe [`History::mark`]
+#[derive(Debug, Clone)]
+pub struct HistoryMark{
+    len: usize,
+    /// Ohne Spur die ganze History von damals
+    earlier: Option<History>
+}
+
+/// Iterator über die Positionen einer [`History`]
+#[derive(Debug, Clone)]
+pub struct Positions<'a>{
+    history: &'a History,
+    /// Der Eintrag, zu dem `position` gehört
+    next: usize,
+    position: Position
+}
+
+impl<'a> Positions<'a>{
+    /// Ohne Spur: die gemerkten Messwerte des nächsten Eintrags
+    fn next_recent(&mut self) -> Option<&'a StepRecord>
+    {
+        let record = self.history.recent.get(self.next - self.history.recent_start)?;
+        self.next += 1;
+        Some(record)
+    }
+
+    fn advance(&mut self)
+    {
+        if self.next < self.history.steps.len() {
+            let (dx, dy) = self.history.steps.get(self.next);
+            self.position.x += dx;
+            self.position.y += dy;
+        }
+        self.next += 1;
+    }
+}
+
+impl Iterator for Positions<'_>{
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Position>
+    {
+        if self.next >= self.history.len() {
+            return None;
+        }
+        if !self.history.trajectory {
+            return self.next_recent().map(|record| record.position.clone());
+        }
+        let position = self.position.clone();
+        self.advance();
+        Some(position)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        let remaining = self.history.len().saturating_sub(self.next);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Positions<'_>{}
+
+/// Die Messwerte eines Eintrags der [`History`]
+#[derive(Debug, Clone)]
+pub struct StepRecord{
+    pub step: usize,
+    pub position: Position,
+    pub distance_from_origin: f32,
+    /// S(t), wie viele verschiedene Felder bis hierher besucht wurden.
+    /// 0, wenn die History keine Spur speichert
+    pub distinct_sites: u32,
+    pub radius_of_gyration: f32,
+    pub hull_area: f32,
+    /// Siehe [`Winding`]
+    pub winding_angle: f32
+}
+
+/// Iterator über die Messwerte einer [`History`], siehe [`History::records`]
+#[derive(Debug, Clone)]
+pub struct Records<'a>{
+    positions: Positions<'a>,
+    from: usize,
+    gyration: Gyration,
+    winding: Winding,
+    distinct_sites: u32,
+    hull_growth: usize
+}
+
+impl Iterator for Records<'_>{
+    type Item = StepRecord;
+
+    fn next(&mut self) -> Option<StepRecord>
+    {
+        let history = self.positions.history;
+        if !history.trajectory {
+            return self.positions.next_recent().cloned();
+        }
+        // Vom Zwischenstand aus werden die Summen bis `from` nachgeholt
+        let (step, position) = loop {
+            let step = self.positions.next;
+            let position = self.positions.next()?;
+            self.gyration.add(&position);
+            self.winding.add(&position);
+            self.distinct_sites += history.is_new_site(step) as u32;
+            if step >= self.from {
+                break (step, position);
+            }
+        };
+        while history.hull_growth.get(self.hull_growth).map_or(false, |(i, _)| *i as usize <= step) {
+            self.hull_growth += 1;
+        }
+        Some(
+            StepRecord{
+                step,
+                distance_from_origin: distance_from_origin(&position),
+                position,
+                distinct_sites: self.distinct_sites,
+                radius_of_gyration: self.gyration.radius_of_gyration() as f32,
+                hull_area: self.hull_growth.checked_sub(1).map_or(0.0, |i| history.hull_growth[i].1),
+                winding_angle: self.winding.angle() as f32
+            }
+        )
     }
 }
 
@@ -58,11 +540,60 @@ impl History{
 /// 
 /// Außerdem befindet sich hier noch ein Random Number Generator (RNG)
 /// der zur erzeugung von Zufallszahlen verwendet werden kann
+/// 
+/// `visits` zählt wie oft der Walker schon auf einem Feld war.
+/// Er wird nur von den selbstabstoßenden Walkern gefüllt.
+/// 
+/// Ist `replay` gesetzt, kommen die Zufallszahlen aus dieser Liste statt vom RNG.
+/// Damit lässt sich ein Walk durch seine Zufallszahlen beschreiben und verändern.
 #[derive(Debug, Clone)]
 pub struct RandomWalker{
     pub ort: Position,
     pub history: History,
     pub rng: Pcg64,
+    pub visits: HashMap<Position, u32>,
+    pub replay: Option<RandomSequence>,
+}
+
+/// Eine feste Folge von Zufallszahlen, die ein Walker der Reihe nach verwendet.
+/// 
+/// Braucht der Walker mehr Zahlen als in der Liste stehen, werden neue vom RNG
+/// gezogen und hinten angehängt. Danach enthält die Liste also alle Zahlen,
+/// die der Walk benutzt hat - und der gleiche Walk lässt sich aus ihr wiederholen.
+/// 
+/// Beispiel:
+/// ```
+/// use girls_day::random_walker::*;
+/// let mut walker = RandomWalker::new(3);
+/// walker.replay = Some(RandomSequence::new(vec![0.1, 0.9]));
+/// walker.random_step();
+/// walker.random_step();
+/// walker.random_step();
+/// // rechts, unten und ein neuer, zufälliger Schritt
+/// assert_eq!(walker.history.position(2), Position { x: 1, y: -1 });
+/// assert_eq!(walker.replay.unwrap().numbers.len(), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RandomSequence{
+    pub numbers: Vec<f64>,
+    position: usize
+}
+
+impl RandomSequence{
+    pub fn new(numbers: Vec<f64>) -> Self
+    {
+        Self { numbers, position: 0 }
+    }
+
+    pub fn next(&mut self, rng: &mut Pcg64) -> f64
+    {
+        if self.position == self.numbers.len() {
+            self.numbers.push(rng.gen());
+        }
+        let number = self.numbers[self.position];
+        self.position += 1;
+        number
+    }
 }
 
 
@@ -96,7 +627,19 @@ impl RandomWalker
         Self { 
             ort: Position { x: 0, y: 0 },
             history: History::with_capacity(capacity), 
-            rng
+            rng,
+            visits: HashMap::new(),
+            replay: None
+        }
+    }
+
+    /// Ein Walker, der nur seine aktuelle Position kennt und keine Spur speichert,
+    /// siehe [`History::without_trajectory`]
+    pub fn without_trajectory(rng: Pcg64) -> Self
+    {
+        Self{
+            history: History::without_trajectory(),
+            ..Self::with_capacity_and_rng(rng, 0)
         }
     }
 
@@ -104,13 +647,16 @@ impl RandomWalker
     /// zufällig im Interval [0,1] liegt.
     /// Beispiel:
     /// ```
-    /// use GirlsDay::random_walker::*;
+    /// use girls_day::random_walker::*;
     /// let mut walker = RandomWalker::new(123);
     /// 
     /// let number = walker.get_random_number();
     /// ```
     pub fn get_random_number(&mut self) -> f64 {
-        self.rng.gen()
+        match &mut self.replay{
+            Some(sequence) => sequence.next(&mut self.rng),
+            None => self.rng.gen()
+        }
     }
 
     /// diese funktion soll einen zufälligen schritt ausführen,
@@ -133,6 +679,12 @@ impl RandomWalker
         self.history.push(alter_ort);
     }
 
+    /// Bias pro Achse: Zuerst wird mit 50/50 entschieden ob der Schritt in x oder y Richtung geht.
+    /// Danach wird mit Wahrscheinlichkeit 0.5 + strength_of_bias vom Ursprung weg gelaufen.
+    /// Liegt der Walker auf der Achse (z.B. x == 0), gibt es für diese Richtung keinen Bias.
+    /// 
+    /// Vergleiche mit [`RandomWalker::random_step_radial_bias_away`], wo der Bias
+    /// entlang der tatsächlichen Richtung zum Ursprung wirkt
     pub fn random_step_biased_away(&mut self, strength_of_bias: f64)
     {
         let probability = self.get_random_number();
@@ -173,6 +725,8 @@ impl RandomWalker
         self.history.push(alter_ort);
     }
 
+    /// Wie [`RandomWalker::random_step_biased_away`], nur läuft der Walker
+    /// mit Wahrscheinlichkeit 0.5 + strength_of_bias zum Ursprung hin
     pub fn random_step_biased_to_origin(&mut self, strength_of_bias: f64)
     {
         let probability = self.get_random_number();
@@ -213,6 +767,168 @@ impl RandomWalker
         self.history.push(alter_ort);
     }
 
+    /// Läuft nach rechts, links, oben oder unten (siehe [`DIRECTIONS`]).
+    /// Die Wahrscheinlichkeiten sind proportional zu den Gewichten,
+    /// die nicht negativ sein dürfen und nicht alle 0 sein dürfen.
+    pub fn step_with_weights(&mut self, weights: &[f64; 4])
+    {
+        let alter_ort = self.ort.clone();
+        let total: f64 = weights.iter().sum();
+        let mut probability = self.get_random_number() * total;
+        let mut direction = DIRECTIONS[3];
+        for (d, weight) in DIRECTIONS.iter().zip(weights){
+            if *weight > 0.0 {
+                direction = *d;
+                probability -= weight;
+                if probability < 0.0 {
+                    break;
+                }
+            }
+        }
+        self.ort.x += direction.0;
+        self.ort.y += direction.1;
+        self.history.push(alter_ort);
+    }
+
+    /// Jede der vier Richtungen hat ihre eigene, feste Wahrscheinlichkeit.
+    /// Die Reihenfolge ist rechts, links, oben, unten.
+    /// So kann man z.B. auch nur eine Achse biasen
+    pub fn random_step_directional(&mut self, probabilities: &[f64; 4])
+    {
+        self.step_with_weights(probabilities);
+    }
+
+    /// Radialer Bias: Die Wahrscheinlichkeit für einen Schritt in Richtung e ist
+    /// 1/4 · (1 + c · e·r), wobei r die Richtung vom Ursprung zum Walker ist.
+    /// 
+    /// Auf den Achsen stimmt das mit [`RandomWalker::random_step_biased_away`] überein,
+    /// auf der Diagonalen wirkt der Bias aber auf beide Achsen gleichzeitig.
+    /// Am Ursprung sind alle Richtungen gleich wahrscheinlich.
+    fn radial_step(&mut self, c: f64)
+    {
+        let direction = (self.ort.x as f64, self.ort.y as f64);
+        self.step_biased_along(direction, c);
+    }
+
+    /// Bevorzugt Schritte entlang von `direction`: Die Wahrscheinlichkeit für einen Schritt
+    /// in Richtung e ist 1/4 · (1 + c · e·u), wobei u der Einheitsvektor von `direction` ist.
+    /// c muss zwischen -1 und 1 liegen, bei negativem c werden Schritte entgegen
+    /// `direction` bevorzugt.
+    fn step_biased_along(&mut self, direction: (f64, f64), c: f64)
+    {
+        self.step_with_weights(&weights_along(direction, c));
+    }
+
+    /// Ein Schritt mit Bias zum Schwerpunkt der anderen Walker hin (coupling > 0)
+    /// oder von ihm weg (coupling < 0). coupling liegt zwischen -1 und 1.
+    pub fn random_step_interacting(&mut self, center_of_others: (f64, f64), coupling: f64)
+    {
+        let direction = (
+            center_of_others.0 - self.ort.x as f64,
+            center_of_others.1 - self.ort.y as f64
+        );
+        self.step_biased_along(direction, coupling);
+    }
+
+    /// Radialer Bias vom Ursprung weg, strength_of_bias liegt zwischen 0 und 0.5
+    pub fn random_step_radial_bias_away(&mut self, strength_of_bias: f64)
+    {
+        self.radial_step(2.0 * strength_of_bias);
+    }
+
+    /// Radialer Bias zum Ursprung hin, strength_of_bias liegt zwischen 0 und 0.5
+    pub fn random_step_radial_bias_to_origin(&mut self, strength_of_bias: f64)
+    {
+        self.radial_step(-2.0 * strength_of_bias);
+    }
+
+    /// Der "wahre" selbstabstoßende Walker (true self-avoiding walk).
+    /// 
+    /// Die Wahrscheinlichkeit auf ein Nachbarfeld zu springen ist 
+    /// proportional zu exp(-g · besuche), wobei besuche zählt wie oft
+    /// der Walker schon auf dem Feld war und g die Stärke der Abstoßung ist.
+    /// Anders als beim strikt selbstvermeidenden Walker kann er nie stecken bleiben.
+    pub fn random_step_true_self_avoiding(&mut self, repulsion: f64)
+    {
+        let Position { x, y } = self.ort;
+        let neighbors = [
+            Position{x: x + 1, y},
+            Position{x: x - 1, y},
+            Position{x, y: y + 1},
+            Position{x, y: y - 1}
+        ];
+        self.self_repelling_step_to(&neighbors, repulsion);
+    }
+
+    /// Wie [`RandomWalker::random_step_true_self_avoiding`], allerdings darf der Walker
+    /// nur nach rechts oder links laufen. 
+    /// In einer Dimension wächst der Abstand wie t^(2/3).
+    pub fn random_step_true_self_avoiding_1d(&mut self, repulsion: f64)
+    {
+        let Position { x, y } = self.ort;
+        let neighbors = [
+            Position{x: x + 1, y},
+            Position{x: x - 1, y}
+        ];
+        self.self_repelling_step_to(&neighbors, repulsion);
+    }
+
+    fn self_repelling_step_to(&mut self, neighbors: &[Position], repulsion: f64)
+    {
+        let alter_ort = self.ort.clone();
+        *self.visits.entry(alter_ort.clone()).or_insert(0) += 1;
+
+        let besuche: Vec<u32> = neighbors.iter()
+            .map(|pos| self.visits.get(pos).copied().unwrap_or(0))
+            .collect();
+        // Nur der Unterschied der Besuche zählt. Ohne das Minimum abzuziehen würde
+        // exp() bei vielen Besuchen für alle Nachbarn 0 werden
+        let min = besuche.iter().copied().min().unwrap_or(0);
+        let weights: Vec<f64> = besuche.iter()
+            .map(|b| (-repulsion * (b - min) as f64).exp())
+            .collect();
+        let total: f64 = weights.iter().sum();
+
+        let mut probability = self.get_random_number() * total;
+        let mut next = neighbors.last().unwrap();
+        for (pos, weight) in neighbors.iter().zip(weights.iter()) {
+            probability -= weight;
+            if probability < 0.0 {
+                next = pos;
+                break;
+            }
+        }
+        self.ort = next.clone();
+        self.history.push(alter_ort);
+    }
+
+    /// Setzt den Walker auf den Eintrag `len` seiner History zurück, als hätte er die
+    /// Schritte danach nie gemacht. `mark` ist ein Stand von vorher, siehe [`History::truncate`].
+    /// Die verbrauchten Zufallszahlen bekommt er dabei nicht zurück
+    pub fn truncate(&mut self, len: usize, mark: &HistoryMark)
+    {
+        if len < self.history.len() {
+            self.ort = self.history.position(len);
+            self.history.truncate(len, mark);
+        }
+    }
+
+    /// Hat der letzte Schritt aus dem Kasten |x|, |y| <= half_width herausgeführt,
+    /// wird er zurückgenommen und der Walker bleibt für diesen Schritt stehen.
+    /// War der Walker schon vorher außerhalb, darf er sich frei bewegen, bis er
+    /// in den Kasten hineinläuft
+    pub fn keep_inside_box(&mut self, half_width: i32)
+    {
+        let inside = |pos: &Position| pos.x.abs() <= half_width && pos.y.abs() <= half_width;
+        if let Some(previous) = self.history.last() {
+            if inside(previous) && !inside(&self.ort) {
+                self.ort = previous.clone();
+            }
+        }
+    }
+
+    /// Hier ist Platz für deine eigene Schrittregel. Die Walker benutzen sie bei
+    /// "Deine Funktion" mit "Code". Als Beispiel springt der Walker wie ein Springer beim Schach
     pub fn your_step_function(&mut self, _strength_of_bias: f64)
     {
         let probability = self.get_random_number();
@@ -248,41 +964,369 @@ impl RandomWalker
     }
 }
 
-#[derive(Debug, Default, Clone)]
+/// Die Gewichte 1 + c · e·u für die vier Richtungen e aus [`DIRECTIONS`],
+/// wobei u der Einheitsvektor von `direction` ist. Ist `direction` der Nullvektor,
+/// sind alle Gewichte 1
+pub fn weights_along(direction: (f64, f64), c: f64) -> [f64; 4]
+{
+    let (x, y) = direction;
+    let r = (x * x + y * y).sqrt();
+    let (ux, uy) = if r > 0.0 {
+        (x / r, y / r)
+    } else {
+        (0.0, 0.0)
+    };
+    DIRECTIONS.map(
+        |(dx, dy)| 1.0 + c * (dx as f64 * ux + dy as f64 * uy)
+    )
+}
+
+/// Die Wahrscheinlichkeiten für rechts, links, oben und unten beim Bias pro Achse,
+/// siehe [`RandomWalker::random_step_biased_away`]. Mit negativem
+/// strength_of_bias ergibt sich [`RandomWalker::random_step_biased_to_origin`]
+pub fn axis_bias_probabilities(pos: &Position, strength_of_bias: f64) -> [f64; 4]
+{
+    let away = |v: i32| (0.5 + v.signum() as f64 * strength_of_bias).clamp(0.0, 1.0);
+    let (px, py) = (away(pos.x), away(pos.y));
+    [0.5 * px, 0.5 * (1.0 - px), 0.5 * py, 0.5 * (1.0 - py)]
+}
+
+/// Lässt alle Walker gleichzeitig einen Schritt machen, wobei jeder Walker
+/// zum Schwerpunkt der anderen hingezogen (coupling > 0) oder von ihm
+/// abgestoßen (coupling < 0) wird.
+/// 
+/// Da die Walker jetzt voneinander abhängen, passiert das in zwei Phasen:
+/// Zuerst werden aus den alten Positionen aller Walker die Schwerpunkte berechnet,
+/// erst danach laufen alle Walker. Da jeder Walker seinen eigenen RNG hat,
+/// ist das Ergebnis trotz paralleler Ausführung immer gleich.
+pub fn step_interacting_walkers(walkers: &mut [RandomWalker], coupling: f64)
+{
+    let n = walkers.len();
+    let (sum_x, sum_y) = walkers.iter()
+        .fold(
+            (0_i64, 0_i64),
+            |(sx, sy), walker| (sx + walker.ort.x as i64, sy + walker.ort.y as i64)
+        );
+    walkers.par_iter_mut()
+        .for_each(
+            |walker|
+            {
+                let center_of_others = if n > 1 {
+                    let others = (n - 1) as f64;
+                    (
+                        (sum_x - walker.ort.x as i64) as f64 / others,
+                        (sum_y - walker.ort.y as i64) as f64 / others
+                    )
+                } else {
+                    (walker.ort.x as f64, walker.ort.y as f64)
+                };
+                walker.random_step_interacting(center_of_others, coupling);
+            }
+        );
+}
+
+/// Mittlerer Abstand der Walker von ihrem gemeinsamen Schwerpunkt.
+/// Ist er klein, bilden die Walker einen Schwarm
+pub fn swarm_radius(walkers: &[RandomWalker]) -> f64
+{
+    let n = walkers.len() as f64;
+    let (sum_x, sum_y) = walkers.iter()
+        .fold(
+            (0.0, 0.0),
+            |(sx, sy), walker| (sx + walker.ort.x as f64, sy + walker.ort.y as f64)
+        );
+    let (cx, cy) = (sum_x / n, sum_y / n);
+    walkers.iter()
+        .map(
+            |walker|
+            {
+                let dx = walker.ort.x as f64 - cx;
+                let dy = walker.ort.y as f64 - cy;
+                (dx * dx + dy * dy).sqrt()
+            }
+        ).sum::<f64>() / n
+}
+
+/// Berechnet Mittelwert und Varianz "im Vorbeigehen" (Welford-Algorithmus),
+/// ohne dass alle Werte gespeichert werden müssen
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Welford{
+    count: u64,
+    mean: f64,
+    m2: f64
+}
+
+impl Welford{
+    pub fn push(&mut self, value: f64)
+    {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Nimmt alle Werte von `other` dazu, als wären sie einzeln mit
+    /// [`Welford::push`] hinzugefügt worden (Chan et al.)
+    ///
+    /// Beispiel:
+    /// ```
+    /// use girls_day::random_walker::Welford;
+    /// let mut all = Welford::default();
+    /// let mut a = Welford::default();
+    /// let mut b = Welford::default();
+    /// for v in [1.0, 4.0, 2.0] { a.push(v); all.push(v); }
+    /// for v in [8.0, 5.0] { b.push(v); all.push(v); }
+    /// a.merge(&b);
+    /// assert_eq!(a.count(), all.count());
+    /// assert!((a.mean() - all.mean()).abs() < 1e-12);
+    /// assert!((a.variance() - all.variance()).abs() < 1e-12);
+    /// ```
+    pub fn merge(&mut self, other: &Welford)
+    {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = *other;
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        let weight = self.count as f64 * other.count as f64 / count as f64;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * weight;
+        self.count = count;
+    }
+
+    pub fn count(&self) -> u64
+    {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64
+    {
+        self.mean
+    }
+
+    /// Stichprobenvarianz, 0 solange es weniger als 2 Werte gibt
+    pub fn variance(&self) -> f64
+    {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// Standardfehler des Mittelwerts
+    pub fn std_error(&self) -> f64
+    {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.variance() / self.count as f64).sqrt()
+        }
+    }
+}
+
+/// Mittelwerte über alle Walker für jeden Schritt:
+/// Der Abstand vom Ursprung, das mittlere Abstandsquadrat
+/// (mean squared displacement, MSD), die Anzahl besuchter Felder S(t),
+/// der Gyrationsradius und die Fläche der konvexen Hülle, jeweils mit Varianz.
+///
+/// Der Eintrag i gehört zum Schritt i · [`AverageDistance::steps_per_entry`].
+/// Speichern die Walker keine Spur, sind die Läufe oft sehr lang. Damit die
+/// Statistik dann nicht mit jedem Schritt wächst, wird bei [`MAX_AVERAGE_ENTRIES`]
+/// Einträgen jeder zweite weggeworfen und danach nur noch jeder zweite Schritt gespeichert.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::ensemble::create_streaming_walkers;
+/// use girls_day::random_walker::AverageDistance;
+/// use girls_day::walk_model::WalkModel;
+/// let mut model = WalkModel::default();
+/// let mut walkers = create_streaming_walkers(3, 2, 0, None);
+/// let mut average = AverageDistance::default();
+/// model.step_and_measure(&mut walkers, 200_000, &mut average);
+/// let stride = average.steps_per_entry();
+/// assert_eq!(stride, 4);
+/// assert_eq!(average.distance.len(), 200_000 / stride);
+/// // Der letzte Eintrag ist genau die Position nach dem Schritt, zu dem er gehört
+/// let last = average.distance.len() - 1;
+/// assert_eq!(average.average_distance_plot_data[last].x, (last * stride) as f64);
+/// ```
+#[derive(Debug, Clone)]
 pub struct AverageDistance{
-    pub average_distance_plot_data: Vec<PlotPoint>
+    pub average_distance_plot_data: Vec<PlotPoint>,
+    pub distance: Vec<Welford>,
+    pub squared_distance: Vec<Welford>,
+    pub distinct_sites: Vec<Welford>,
+    pub radius_of_gyration: Vec<Welford>,
+    pub hull_area: Vec<Welford>,
+    /// Zu wie vielen Schritten ein Eintrag gehört
+    steps_per_entry: usize
+}
+
+impl Default for AverageDistance{
+    fn default() -> Self {
+        Self{
+            average_distance_plot_data: Vec::new(),
+            distance: Vec::new(),
+            squared_distance: Vec::new(),
+            distinct_sites: Vec::new(),
+            radius_of_gyration: Vec::new(),
+            hull_area: Vec::new(),
+            steps_per_entry: 1
+        }
+    }
 }
 
 impl AverageDistance{
 
-    pub fn push_averages(&mut self, averages: &[f32]){
-        let start = self.average_distance_plot_data.len();
-        self.average_distance_plot_data
-            .extend(
-                averages
-                    .iter()
-                    .zip(start..)
-                    .map(|(y, x)| PlotPoint{x: x as f64, y: *y as f64})
-            );
+    /// Der Eintrag i der Statistiken gehört zum Schritt i · steps_per_entry.
+    /// Immer eine Zweierpotenz, 1 solange nichts weggeworfen wurde
+    pub fn steps_per_entry(&self) -> usize
+    {
+        self.steps_per_entry
     }
 
     pub fn update_on_step_of_walkers(&mut self, number_of_steps: usize, walkers: &[RandomWalker])
     {
-        let mut sums = vec![0.0; number_of_steps];
         let idx_start = walkers[0].history.len() - number_of_steps;
-        let num_of_walkers = walkers.len();
+        // Walker ohne Spur zählen ihre besuchten Felder nicht
+        let count_sites = walkers.iter().all(|walker| walker.history.keeps_trajectory());
 
-        for walker in walkers{
-            let new_additions_slice = &walker.history.distance_from_origin[idx_start..];
-            for i in 0..sums.len()
-            {
-                sums[i] += new_additions_slice[i];
+        // Die Werte werden Stück für Stück aus den Histories gelesen,
+        // damit nicht alle neuen Schritte aller Walker gleichzeitig im Speicher liegen
+        let mut records: Vec<Records> = walkers.iter()
+            .map(|walker| walker.history.records(idx_start))
+            .collect();
+        let chunk = (MAX_RECORDS_PER_CHUNK / walkers.len()).max(1);
+        let mut done = 0;
+        while done < number_of_steps {
+            let steps = chunk.min(number_of_steps - done);
+            // Pro Walker und Schritt: Abstand, Abstandsquadrat, besuchte Felder, Gyrationsradius, Hüllenfläche
+            let values: Vec<Vec<[f64; 5]>> = records.par_iter_mut()
+                .map(
+                    |records|
+                    {
+                        records.take(steps)
+                            .map(
+                                |record|
+                                {
+                                    let pos = &record.position;
+                                    let squared = pos.x as f64 * pos.x as f64 + pos.y as f64 * pos.y as f64;
+                                    [
+                                        record.distance_from_origin as f64,
+                                        squared,
+                                        record.distinct_sites as f64,
+                                        record.radius_of_gyration as f64,
+                                        record.hull_area as f64
+                                    ]
+                                }
+                            ).collect()
+                    }
+                ).collect();
+            let stats: Vec<[Welford; 5]> = (0..steps)
+                .into_par_iter()
+                .map(
+                    |i|
+                    {
+                        let mut stats: [Welford; 5] = Default::default();
+                        for walker_values in &values{
+                            for (w, value) in stats.iter_mut().zip(walker_values[i]){
+                                w.push(value);
+                            }
+                        }
+                        stats
+                    }
+                ).collect();
+            self.push_stats(idx_start + done, stats, count_sites);
+            done += steps;
+        }
+    }
+
+    /// Speichert die Statistiken der Schritte ab `first_step`, aber nur die,
+    /// die zu einem Eintrag gehören
+    fn push_stats(&mut self, first_step: usize, stats: Vec<[Welford; 5]>, count_sites: bool)
+    {
+        for (step, [distance, squared_distance, distinct_sites, radius_of_gyration, hull_area]) in (first_step..).zip(stats){
+            if step % self.steps_per_entry != 0 {
+                continue;
             }
+            self.average_distance_plot_data.push(PlotPoint { x: step as f64, y: distance.mean() });
+            self.distance.push(distance);
+            self.squared_distance.push(squared_distance);
+            if count_sites {
+                self.distinct_sites.push(distinct_sites);
+            }
+            self.radius_of_gyration.push(radius_of_gyration);
+            self.hull_area.push(hull_area);
+            // Ohne Spur sind die Läufe oft sehr lang, dann wird ausgedünnt
+            if !count_sites && self.distance.len() >= MAX_AVERAGE_ENTRIES {
+                self.thin_out(2 * self.steps_per_entry);
+            }
+        }
+    }
+
+    /// Behält nur die Einträge, deren Schritt ein Vielfaches von `steps_per_entry` ist
+    fn thin_out(&mut self, steps_per_entry: usize)
+    {
+        fn keep_every<T>(entries: &mut Vec<T>, factor: usize)
+        {
+            let mut index = 0;
+            entries.retain(
+                |_|
+                {
+                    index += 1;
+                    (index - 1) % factor == 0
+                }
+            );
+            entries.shrink_to_fit();
         }
-        
-        sums.iter_mut()
-            .for_each(|val| *val /= num_of_walkers as f32);
-        self.push_averages(&sums);
+        if steps_per_entry <= self.steps_per_entry {
+            return;
+        }
+        let factor = steps_per_entry / self.steps_per_entry;
+        keep_every(&mut self.average_distance_plot_data, factor);
+        keep_every(&mut self.distance, factor);
+        keep_every(&mut self.squared_distance, factor);
+        keep_every(&mut self.distinct_sites, factor);
+        keep_every(&mut self.radius_of_gyration, factor);
+        keep_every(&mut self.hull_area, factor);
+        self.steps_per_entry = steps_per_entry;
+    }
+
+    /// Nimmt die Statistiken eines anderen, unabhängigen Laufs dazu.
+    /// Ist der andere Lauf länger, kommen seine zusätzlichen Schritte einfach dazu
+    pub fn merge(&mut self, other: &AverageDistance)
+    {
+        fn merge_stats(stats: &mut Vec<Welford>, other: &[Welford])
+        {
+            for (w, o) in stats.iter_mut().zip(other){
+                w.merge(o);
+            }
+            if other.len() > stats.len() {
+                stats.extend_from_slice(&other[stats.len()..]);
+            }
+        }
+        // Wurden die Läufe verschieden stark ausgedünnt, zählen nur die gemeinsamen Schritte
+        let thinned;
+        let other = if other.steps_per_entry < self.steps_per_entry {
+            let mut copy = other.clone();
+            copy.thin_out(self.steps_per_entry);
+            thinned = copy;
+            &thinned
+        } else {
+            self.thin_out(other.steps_per_entry);
+            other
+        };
+        merge_stats(&mut self.distance, &other.distance);
+        merge_stats(&mut self.squared_distance, &other.squared_distance);
+        merge_stats(&mut self.distinct_sites, &other.distinct_sites);
+        merge_stats(&mut self.radius_of_gyration, &other.radius_of_gyration);
+        merge_stats(&mut self.hull_area, &other.hull_area);
+        self.average_distance_plot_data = Self::means(&self.distance, self.steps_per_entry, 1);
     }
 
     pub fn cloned_average(&self) -> Vec<PlotPoint>
@@ -298,4 +1342,49 @@ impl AverageDistance{
             .copied()
             .collect()
     }
-}
\ No newline at end of file
+
+    /// Das mittlere Abstandsquadrat, nur jeder `step_by`-te Punkt
+    pub fn msd(&self, step_by: usize) -> Vec<PlotPoint>
+    {
+        Self::means(&self.squared_distance, self.steps_per_entry, step_by)
+    }
+
+    /// Die Mittelwerte von `stats`, nur jeder `step_by`-te Eintrag.
+    /// Ein Eintrag gehört zu `steps_per_entry` Schritten
+    pub fn means(stats: &[Welford], steps_per_entry: usize, step_by: usize) -> Vec<PlotPoint>
+    {
+        stats.iter()
+            .enumerate()
+            .step_by(step_by)
+            .map(|(i, w)| PlotPoint { x: (i * steps_per_entry) as f64, y: w.mean() })
+            .collect()
+    }
+
+    /// Der Bereich Mittelwert ± Standardfehler, zerlegt in Vierecke.
+    /// egui kann nur konvexe Polygone füllen, daher wird nicht ein einziges
+    /// großes Polygon zurückgegeben
+    pub fn error_band(stats: &[Welford], steps_per_entry: usize, step_by: usize) -> Vec<[[f64; 2]; 4]>
+    {
+        Self::confidence_band(stats, steps_per_entry, step_by, 1.0)
+    }
+
+    /// Wie [`AverageDistance::error_band`], aber mit Mittelwert ± z · Standardfehler.
+    /// Mit z = 1.96 liegt der wahre Mittelwert zu 95 % im Band
+    pub fn confidence_band(stats: &[Welford], steps_per_entry: usize, step_by: usize, z: f64) -> Vec<[[f64; 2]; 4]>
+    {
+        let corners: Vec<_> = stats.iter()
+            .enumerate()
+            .step_by(step_by)
+            .map(
+                |(i, w)|
+                {
+                    let x = (i * steps_per_entry) as f64;
+                    let half_width = z * w.std_error();
+                    ([x, w.mean() - half_width], [x, w.mean() + half_width])
+                }
+            ).collect();
+        corners.windows(2)
+            .map(|pair| [pair[0].0, pair[1].0, pair[1].1, pair[0].1])
+            .collect()
+    }
+}
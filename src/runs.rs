@@ -0,0 +1,212 @@
+use egui::{plot::*, Color32};
+
+use crate::ensemble::{Batch, BatchProgress};
+use crate::random_walker::{AverageDistance, Welford};
+use crate::walk_model::WalkModel;
+
+/// Mit ± 1.96 Standardfehlern liegt der wahre Mittelwert zu 95 % im Band
+const Z_95: f64 = 1.96;
+
+/// Viele unabhängige Läufe hintereinander, deren Statistiken zusammengezählt werden.
+///
+/// Von jedem Lauf werden nur die Mittelwerte und Varianzen behalten, die Walker
+/// selbst werden danach weggeworfen. So kann man über viel mehr Walker mitteln,
+/// als gleichzeitig in den Speicher passen.
+#[derive(Debug, Clone)]
+pub struct RepeatedRuns{
+    pub runs_to_add: usize,
+    pub steps_per_run: usize,
+    pending_runs: usize,
+    next_run: u64,
+    /// Der Lauf, der gerade simuliert wird
+    batch: Option<Batch>,
+    pub runs: usize,
+    pub walkers: usize,
+    pub accumulated: AverageDistance,
+    /// Der mittlere Abstand beim letzten Schritt nach jedem Lauf
+    convergence: Vec<(usize, Welford)>
+}
+
+impl Default for RepeatedRuns{
+    fn default() -> Self {
+        Self{
+            runs_to_add: 50,
+            steps_per_run: 1000,
+            pending_runs: 0,
+            next_run: 1,
+            batch: None,
+            runs: 0,
+            walkers: 0,
+            accumulated: AverageDistance::default(),
+            convergence: Vec::new()
+        }
+    }
+}
+
+impl RepeatedRuns{
+    /// Vergisst alle gesammelten Läufe
+    pub fn reset(&mut self)
+    {
+        *self = Self{
+            runs_to_add: self.runs_to_add,
+            steps_per_run: self.steps_per_run,
+            ..Self::default()
+        };
+    }
+
+    pub fn is_busy(&self) -> bool
+    {
+        self.pending_runs > 0
+    }
+
+    /// Rechnet am aktuellen Lauf weiter. Ist er fertig, werden seine
+    /// Statistiken zu den bisherigen addiert und er wird weggeworfen.
+    ///
+    /// Jeder Lauf bekommt seinen eigenen seed: seed + 1, seed + 2, ...
+    pub fn run_pending(&mut self, model: &mut WalkModel, seed: u64, num_of_walkers: usize)
+    {
+        if self.pending_runs == 0 {
+            return;
+        }
+        let steps_per_run = self.steps_per_run;
+        let next_run = self.next_run;
+        let batch = self.batch.get_or_insert_with(|| Batch::new(seed.wrapping_add(next_run), num_of_walkers, steps_per_run));
+        match batch.advance(model) {
+            BatchProgress::Running => {},
+            BatchProgress::Failed => {
+                self.pending_runs = 0;
+                self.batch = None;
+            },
+            BatchProgress::Finished => {
+                let batch = self.batch.take().unwrap();
+                self.accumulated.merge(&batch.stats);
+                self.walkers += batch.walkers.len();
+                self.runs += 1;
+                self.next_run += 1;
+                self.pending_runs -= 1;
+                if let Some(last) = self.accumulated.distance.last() {
+                    self.convergence.push((self.walkers, *last));
+                }
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, num_of_walkers: usize)
+    {
+        ui.horizontal(
+            |ui|
+            {
+                ui.add(egui::DragValue::new(&mut self.runs_to_add).clamp_range(1..=100000));
+                let button = egui::Button::new(format!("Läufe mit je {num_of_walkers} Walkern"));
+                if ui.add_enabled(!self.is_busy(), button)
+                    .on_hover_text("Simuliert nacheinander neue, unabhängige Walker mit den aktuellen Einstellungen und sammelt ihre Mittelwerte")
+                    .clicked()
+                {
+                    self.pending_runs = self.runs_to_add;
+                }
+                ui.label("Schritte pro Lauf");
+                let steps = ui.add_enabled(
+                    !self.is_busy(),
+                    egui::DragValue::new(&mut self.steps_per_run)
+                        .clamp_range(1..=500000)
+                        .speed(10.0)
+                );
+                if steps.changed() {
+                    self.reset();
+                }
+                if self.is_busy() {
+                    ui.spinner();
+                    let step = self.batch.as_ref().map_or(0, |batch| batch.walkers[0].history.len());
+                    ui.label(format!("noch {} Läufe, Schritt {step}", self.pending_runs));
+                    if ui.button("Stopp").clicked(){
+                        self.pending_runs = 0;
+                        self.batch = None;
+                    }
+                }
+                if ui.button("Vergessen").clicked(){
+                    self.reset();
+                }
+            }
+        );
+
+        let Some(last) = self.accumulated.distance.last() else {
+            ui.label("Noch keine Daten");
+            return;
+        };
+        ui.label(
+            format!(
+                "{} Läufe mit zusammen {} Walkern. Mittlerer Abstand bei Schritt {}: {:.3} ± {:.3} (95 %)",
+                self.runs,
+                self.walkers,
+                (self.accumulated.distance.len() - 1) * self.accumulated.steps_per_entry(),
+                last.mean(),
+                Z_95 * last.std_error()
+            )
+        ).on_hover_text("Das Band ist das 95 %-Konfidenzintervall: Mit mehr Walkern wird es schmaler wie 1/√(Anzahl)");
+
+        let height = (ui.available_height() / 2.0 - 20.0).max(50.0);
+        let color = Color32::from_rgb(0, 150, 255);
+        ui.label("Mittlerer Abstand über alle Läufe");
+        let stats = &self.accumulated.distance;
+        let steps_per_entry = self.accumulated.steps_per_entry();
+        let band_stride = (stats.len() / 500).max(1);
+        let band = AverageDistance::confidence_band(stats, steps_per_entry, band_stride, Z_95);
+        let average = AverageDistance::means(stats, steps_per_entry, (stats.len() / 2000).max(1));
+        Plot::new("repeated_runs_average")
+            .legend(Legend::default())
+            .include_x(0.0)
+            .height(height)
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    for quad in band{
+                        plot_ui.polygon(band_polygon(quad, color));
+                    }
+                    plot_ui.line(Line::new(PlotPoints::Owned(average)).name("average").color(color));
+                }
+            );
+
+        ui.label("Mittlerer Abstand beim letzten Schritt, je mehr Walker desto genauer");
+        let convergence: Vec<PlotPoint> = self.convergence.iter()
+            .map(|(walkers, w)| PlotPoint { x: *walkers as f64, y: w.mean() })
+            .collect();
+        let band: Vec<[[f64; 2]; 4]> = self.convergence.windows(2)
+            .map(
+                |pair|
+                {
+                    let corner = |(walkers, w): &(usize, Welford), sign: f64| {
+                        [*walkers as f64, w.mean() + sign * Z_95 * w.std_error()]
+                    };
+                    [corner(&pair[0], -1.0), corner(&pair[1], -1.0), corner(&pair[1], 1.0), corner(&pair[0], 1.0)]
+                }
+            ).collect();
+        Plot::new("repeated_runs_convergence")
+            .legend(Legend::default())
+            .include_x(0.0)
+            .height(height)
+            .label_formatter(|name, p| format!("{name}\n{:.0} Walker\nAbstand = {:.3}", p.x, p.y))
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    for quad in band{
+                        plot_ui.polygon(band_polygon(quad, color));
+                    }
+                    plot_ui.line(Line::new(PlotPoints::Owned(convergence)).name("average").color(color));
+                }
+            );
+    }
+}
+
+fn band_polygon(quad: [[f64; 2]; 4], color: Color32) -> Polygon
+{
+    let corners = quad.iter()
+        .map(|[x, y]| PlotPoint { x: *x, y: *y })
+        .collect();
+    Polygon::new(PlotPoints::Owned(corners))
+        .name("95 %-Konfidenzintervall")
+        .color(color)
+        .fill_alpha(0.3)
+        .width(0.0)
+}
@@ -0,0 +1,163 @@
+use crate::random_walker::Position;
+
+/// z-Komponente des Kreuzprodukts (a - o) × (b - o).
+/// Positiv, wenn o → a → b eine Linkskurve ist
+fn cross(o: (i64, i64), a: (i64, i64), b: (i64, i64)) -> i64
+{
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Eine Hälfte der Hülle für nach x sortierte Punkte.
+/// Der letzte Punkt fehlt, er ist der Anfang der anderen Hälfte
+fn half_hull<'a>(points: impl Iterator<Item = &'a (i64, i64)>) -> Vec<(i64, i64)>
+{
+    let mut half: Vec<(i64, i64)> = Vec::new();
+    for &p in points{
+        while half.len() >= 2 && cross(half[half.len() - 2], half[half.len() - 1], p) <= 0 {
+            half.pop();
+        }
+        half.push(p);
+    }
+    half.pop();
+    half
+}
+
+/// Die konvexe Hülle aller bisher hinzugefügten Punkte.
+///
+/// Die Ecken sind gegen den Uhrzeigersinn sortiert. Liegt ein neuer Punkt
+/// in der Hülle, ändert sich nichts. Sonst wird die Hülle aus den alten Ecken
+/// und dem neuen Punkt neu berechnet (Andrew's monotone chain) - da die Hülle
+/// nur wenige Ecken hat, ist das schnell.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::shape::ConvexHull;
+/// use girls_day::random_walker::Position;
+/// let mut hull = ConvexHull::default();
+/// for (x, y) in [(0, 0), (2, 0), (1, 1), (2, 2), (0, 2), (1, 0)] {
+///     hull.add(&Position { x, y });
+/// }
+/// assert_eq!(hull.area(), 4.0);
+/// assert_eq!(hull.corners().len(), 4);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConvexHull{
+    corners: Vec<(i64, i64)>,
+    double_area: i64
+}
+
+impl ConvexHull{
+    pub fn corners(&self) -> &[(i64, i64)]
+    {
+        &self.corners
+    }
+
+    pub fn area(&self) -> f64
+    {
+        self.double_area as f64 / 2.0
+    }
+
+    pub fn contains(&self, point: (i64, i64)) -> bool
+    {
+        match self.corners.len(){
+            0 => false,
+            1 | 2 => {
+                // Punkt oder Strecke
+                let a = self.corners[0];
+                let b = *self.corners.last().unwrap();
+                cross(a, b, point) == 0
+                    && (a.0.min(b.0)..=a.0.max(b.0)).contains(&point.0)
+                    && (a.1.min(b.1)..=a.1.max(b.1)).contains(&point.1)
+            },
+            n => (0..n).all(|i| cross(self.corners[i], self.corners[(i + 1) % n], point) >= 0)
+        }
+    }
+
+    pub fn add(&mut self, pos: &Position)
+    {
+        let point = (pos.x as i64, pos.y as i64);
+        if self.contains(point) {
+            return;
+        }
+        let mut points = std::mem::take(&mut self.corners);
+        points.push(point);
+        points.sort_unstable();
+        points.dedup();
+
+        let hull = if points.len() < 3 {
+            points
+        } else {
+            // Untere Hälfte von links nach rechts, obere Hälfte zurück
+            let mut hull = half_hull(points.iter());
+            hull.extend(half_hull(points.iter().rev()));
+            hull
+        };
+        self.double_area = (0..hull.len())
+            .map(
+                |i|
+                {
+                    let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+                    a.0 * b.1 - b.0 * a.1
+                }
+            ).sum();
+        self.corners = hull;
+    }
+}
+
+/// Die Summen, aus denen sich der Gyrationsradius berechnet.
+/// Sie sind klein und lassen sich kopieren, z.B. für Zwischenstände der History.
+/// Die Summen stehen in i128, damit sie auch bei Milliarden Schritten weit weg
+/// vom Ursprung nicht überlaufen und trotzdem exakt bleiben
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gyration{
+    count: i64,
+    sum_x: i128,
+    sum_y: i128,
+    sum_squares: i128
+}
+
+impl Gyration{
+    pub fn add(&mut self, pos: &Position)
+    {
+        let (x, y) = (pos.x as i128, pos.y as i128);
+        self.count += 1;
+        self.sum_x += x;
+        self.sum_y += y;
+        self.sum_squares += x * x + y * y;
+    }
+
+    /// Gyrationsradius: Wurzel des mittleren Abstandsquadrats aller
+    /// bisherigen Positionen von ihrem Schwerpunkt
+    pub fn radius_of_gyration(&self) -> f64
+    {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let n = self.count as f64;
+        let mean_x = self.sum_x as f64 / n;
+        let mean_y = self.sum_y as f64 / n;
+        let squared = self.sum_squares as f64 / n - mean_x * mean_x - mean_y * mean_y;
+        squared.max(0.0).sqrt()
+    }
+}
+
+/// Formmaße der Trajektorie, die mit jedem Schritt aktualisiert werden:
+/// Der Gyrationsradius und die konvexe Hülle
+#[derive(Debug, Clone, Default)]
+pub struct Shape{
+    pub gyration: Gyration,
+    pub hull: ConvexHull
+}
+
+impl Shape{
+    pub fn add(&mut self, pos: &Position)
+    {
+        self.gyration.add(pos);
+        self.hull.add(pos);
+    }
+
+    pub fn radius_of_gyration(&self) -> f64
+    {
+        self.gyration.radius_of_gyration()
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use crate::random_walker::DIRECTIONS;
+
+/// Ab so vielen verschiedenen Schritten wird der Index über eine HashMap gesucht
+const LINEAR_SEARCH_LIMIT: usize = 16;
+
+/// Wie viele u64 man für `count` Zahlen mit je `bits` Bits braucht
+fn words_for(count: usize, bits: u32) -> usize
+{
+    let per_word = (64 / bits) as usize;
+    (count + per_word - 1) / per_word
+}
+
+/// Kleine ganze Zahlen, dicht in u64 gepackt.
+///
+/// Jede Zahl braucht `bits` Bits, anfangs 2. Kommt eine Zahl, die nicht mehr
+/// hineinpasst, werden alle Zahlen mit doppelt so vielen Bits neu gepackt.
+/// Da `bits` immer 64 teilt, liegt jede Zahl vollständig in einem u64
+#[derive(Debug, Clone)]
+pub struct PackedCodes{
+    bits: u32,
+    len: usize,
+    words: Vec<u64>
+}
+
+impl PackedCodes{
+    pub fn with_capacity(capacity: usize) -> Self
+    {
+        let bits = 2;
+        Self{
+            bits,
+            len: 0,
+            words: Vec::with_capacity(words_for(capacity, bits))
+        }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+
+    pub fn bits(&self) -> u32
+    {
+        self.bits
+    }
+
+    /// Für wie viele Zahlen mit der aktuellen Breite schon Platz reserviert ist
+    pub fn capacity(&self) -> usize
+    {
+        self.words.capacity() * (64 / self.bits) as usize
+    }
+
+    fn per_word(&self) -> usize
+    {
+        (64 / self.bits) as usize
+    }
+
+    fn mask(&self) -> u64
+    {
+        (1 << self.bits) - 1
+    }
+
+    pub fn get(&self, index: usize) -> u32
+    {
+        debug_assert!(index < self.len);
+        let per_word = self.per_word();
+        let shift = (index % per_word) as u32 * self.bits;
+        ((self.words[index / per_word] >> shift) & self.mask()) as u32
+    }
+
+    pub fn push(&mut self, code: u32)
+    {
+        let needed = u32::BITS - code.leading_zeros();
+        if needed > self.bits {
+            self.widen(needed.next_power_of_two());
+        }
+        let per_word = self.per_word();
+        let shift = (self.len % per_word) as u32 * self.bits;
+        if shift == 0 {
+            self.words.push(0);
+        }
+        *self.words.last_mut().unwrap() |= (code as u64) << shift;
+        self.len += 1;
+    }
+
+    fn widen(&mut self, bits: u32)
+    {
+        let mut wider = Self{
+            bits,
+            len: 0,
+            words: Vec::with_capacity(words_for(self.capacity(), bits))
+        };
+        for i in 0..self.len{
+            wider.push(self.get(i));
+        }
+        *self = wider;
+    }
+}
+
+/// Die Schritte eines Walkers, jeder als Index in die Liste der bisher vorgekommenen
+/// Schritte (dx, dy).
+///
+/// Die vier Schritte des Quadratgitters stehen von Anfang an in der Liste, daher
+/// braucht ein normaler Random Walk nur 2 Bit pro Schritt. Springerzüge oder
+/// Stehenbleiben am Rand des Kastens machen die Liste länger, dann werden es 4 Bit
+/// und so weiter.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::step_encoding::StepEncoding;
+/// let mut steps = StepEncoding::with_capacity(100);
+/// for _ in 0..25 {
+///     for step in [(1, 0), (0, -1), (-1, 0), (0, 1)] {
+///         steps.push(step);
+///     }
+/// }
+/// assert_eq!(steps.bits_per_step(), 2);
+/// steps.push((1, 2));
+/// steps.push((0, 0));
+/// assert_eq!(steps.bits_per_step(), 4);
+/// assert_eq!(steps.get(1), (0, -1));
+/// assert_eq!(steps.get(100), (1, 2));
+/// assert_eq!(steps.get(101), (0, 0));
+/// assert_eq!(steps.len(), 102);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StepEncoding{
+    moves: Vec<(i32, i32)>,
+    index: HashMap<(i32, i32), u32>,
+    codes: PackedCodes
+}
+
+impl StepEncoding{
+    pub fn with_capacity(capacity: usize) -> Self
+    {
+        let moves = DIRECTIONS.to_vec();
+        let index = moves.iter()
+            .zip(0..)
+            .map(|(step, code)| (*step, code))
+            .collect();
+        Self { moves, index, codes: PackedCodes::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.codes.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize
+    {
+        self.codes.capacity()
+    }
+
+    pub fn bits_per_step(&self) -> u32
+    {
+        self.codes.bits()
+    }
+
+    fn code(&mut self, step: (i32, i32)) -> u32
+    {
+        let known = if self.moves.len() <= LINEAR_SEARCH_LIMIT {
+            self.moves.iter().position(|m| *m == step).map(|i| i as u32)
+        } else {
+            self.index.get(&step).copied()
+        };
+        known.unwrap_or_else(
+            ||
+            {
+                let code = self.moves.len() as u32;
+                self.moves.push(step);
+                self.index.insert(step, code);
+                code
+            }
+        )
+    }
+
+    pub fn push(&mut self, step: (i32, i32))
+    {
+        let code = self.code(step);
+        self.codes.push(code);
+    }
+
+    pub fn get(&self, index: usize) -> (i32, i32)
+    {
+        self.moves[self.codes.get(index) as usize]
+    }
+}
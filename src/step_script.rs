@@ -0,0 +1,686 @@
+use std::fmt;
+
+use crate::random_walker::RandomWalker;
+
+/// Das Beispiel, das beim ersten Start im Editor steht
+pub const EXAMPLE_SCRIPT: &str = "// Auf der rechten Seite zieht es den Walker nach links
+// und auf der linken Seite nach rechts
+if ort.x > 0 {
+    links: 0.3, rechts: 0.2, oben: 0.25, unten: 0.25
+} else if ort.x < 0 {
+    links: 0.2, rechts: 0.3, oben: 0.25, unten: 0.25
+} else {
+    links: 0.25, rechts: 0.25, oben: 0.25, unten: 0.25
+}";
+
+/// Kurze Anleitung, die neben dem Editor angezeigt wird
+pub const SCRIPT_HELP: &str = "Richtungen: links, rechts, oben, unten (oder left, right, up, down)
+Variablen: ort.x, ort.y (oder x, y), schritt, abstand, zufall
+Funktionen: abs, sqrt, exp, ln, sin, cos, min, max
+Rechnen: + - * / % ^   Vergleiche: < <= > >= == !=   Logik: && || !
+Nicht genannte Richtungen haben das Gewicht 0, die Gewichte werden automatisch normiert.";
+
+/// Wie tief Klammern und if-Blöcke verschachtelt sein dürfen
+const MAX_DEPTH: usize = 64;
+
+/// Ein Fehler beim Einlesen des Skripts.
+/// Zeile und Spalte beginnen bei 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError{
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for ScriptError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Zeile {}, Spalte {}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Die Reihenfolge entspricht [`crate::random_walker::DIRECTIONS`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction{
+    Right,
+    Left,
+    Up,
+    Down
+}
+
+impl Direction{
+    fn from_name(name: &str) -> Option<Self>
+    {
+        match name{
+            "links" | "left" => Some(Direction::Left),
+            "rechts" | "right" => Some(Direction::Right),
+            "oben" | "up" => Some(Direction::Up),
+            "unten" | "down" => Some(Direction::Down),
+            _ => None
+        }
+    }
+
+    fn index(&self) -> usize
+    {
+        *self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable{
+    X,
+    Y,
+    Step,
+    Distance,
+    Random
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function{
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Sin,
+    Cos,
+    Min,
+    Max
+}
+
+impl Function{
+    fn from_name(name: &str) -> Option<Self>
+    {
+        match name{
+            "abs" => Some(Function::Abs),
+            "sqrt" => Some(Function::Sqrt),
+            "exp" => Some(Function::Exp),
+            "ln" => Some(Function::Ln),
+            "sin" => Some(Function::Sin),
+            "cos" => Some(Function::Cos),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            _ => None
+        }
+    }
+
+    fn arity(&self) -> usize
+    {
+        match self{
+            Function::Min | Function::Max => 2,
+            _ => 1
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp{
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    Equal,
+    NotEqual,
+    And,
+    Or
+}
+
+#[derive(Debug, Clone)]
+enum Expr{
+    Number(f64),
+    Variable(Variable),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>)
+}
+
+#[derive(Debug, Clone)]
+enum Rule{
+    Weights([Option<Expr>; 4]),
+    If{
+        condition: Expr,
+        then: Box<Rule>,
+        otherwise: Box<Rule>
+    }
+}
+
+/// Alles was ein Skript über den Walker wissen darf
+struct Context<'a>{
+    x: f64,
+    y: f64,
+    step: f64,
+    random: &'a mut dyn FnMut() -> f64
+}
+
+impl Expr{
+    fn eval(&self, ctx: &mut Context) -> f64
+    {
+        let truth = |b: bool| if b { 1.0 } else { 0.0 };
+        match self{
+            Expr::Number(n) => *n,
+            Expr::Variable(v) => {
+                match v{
+                    Variable::X => ctx.x,
+                    Variable::Y => ctx.y,
+                    Variable::Step => ctx.step,
+                    Variable::Distance => (ctx.x * ctx.x + ctx.y * ctx.y).sqrt(),
+                    Variable::Random => (ctx.random)()
+                }
+            },
+            Expr::Negate(e) => -e.eval(ctx),
+            Expr::Not(e) => truth(e.eval(ctx) == 0.0),
+            Expr::Binary(op, a, b) => {
+                // && und || werten die rechte Seite nur aus, wenn es nötig ist
+                match op{
+                    BinaryOp::And => return truth(a.eval(ctx) != 0.0 && b.eval(ctx) != 0.0),
+                    BinaryOp::Or => return truth(a.eval(ctx) != 0.0 || b.eval(ctx) != 0.0),
+                    _ => ()
+                }
+                let a = a.eval(ctx);
+                let b = b.eval(ctx);
+                match op{
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Rem => a % b,
+                    BinaryOp::Pow => a.powf(b),
+                    BinaryOp::Less => truth(a < b),
+                    BinaryOp::LessEq => truth(a <= b),
+                    BinaryOp::Greater => truth(a > b),
+                    BinaryOp::GreaterEq => truth(a >= b),
+                    BinaryOp::Equal => truth(a == b),
+                    BinaryOp::NotEqual => truth(a != b),
+                    BinaryOp::And | BinaryOp::Or => unreachable!()
+                }
+            },
+            Expr::Call(f, args) => {
+                let a = args[0].eval(ctx);
+                match f{
+                    Function::Abs => a.abs(),
+                    Function::Sqrt => a.sqrt(),
+                    Function::Exp => a.exp(),
+                    Function::Ln => a.ln(),
+                    Function::Sin => a.sin(),
+                    Function::Cos => a.cos(),
+                    Function::Min => a.min(args[1].eval(ctx)),
+                    Function::Max => a.max(args[1].eval(ctx))
+                }
+            }
+        }
+    }
+}
+
+impl Rule{
+    fn eval(&self, ctx: &mut Context) -> Result<[f64; 4], String>
+    {
+        match self{
+            Rule::If { condition, then, otherwise } => {
+                if condition.eval(ctx) != 0.0 {
+                    then.eval(ctx)
+                } else {
+                    otherwise.eval(ctx)
+                }
+            },
+            Rule::Weights(exprs) => {
+                let mut weights = [0.0; 4];
+                for (weight, expr) in weights.iter_mut().zip(exprs.iter()){
+                    if let Some(expr) = expr{
+                        *weight = expr.eval(ctx);
+                        if !weight.is_finite() || *weight < 0.0 {
+                            return Err(
+                                format!(
+                                    "Bei ort.x = {}, ort.y = {} ist ein Gewicht {} - Gewichte müssen positiv sein",
+                                    ctx.x, ctx.y, weight
+                                )
+                            );
+                        }
+                    }
+                }
+                if weights.iter().sum::<f64>() <= 0.0 {
+                    return Err(
+                        format!(
+                            "Bei ort.x = {}, ort.y = {} sind alle Gewichte 0",
+                            ctx.x, ctx.y
+                        )
+                    );
+                }
+                Ok(weights)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token{
+    Number(f64),
+    Ident(String),
+    Symbol(&'static str),
+    End
+}
+
+impl fmt::Display for Token{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self{
+            Token::Number(n) => write!(f, "'{n}'"),
+            Token::Ident(name) => write!(f, "'{name}'"),
+            Token::Symbol(s) => write!(f, "'{s}'"),
+            Token::End => write!(f, "das Ende")
+        }
+    }
+}
+
+const SYMBOLS: [&str; 22] = [
+    "<=", ">=", "==", "!=", "&&", "||",
+    "<", ">", "+", "-", "*", "/", "%", "^", "!",
+    "(", ")", "{", "}", ",", ":", "."
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize, usize)>, ScriptError>
+{
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut column = 1;
+    let mut rest = source;
+
+    while !rest.is_empty(){
+        let c = rest.chars().next().unwrap();
+        if c == '\n' {
+            line += 1;
+            column = 1;
+            rest = &rest[1..];
+            continue;
+        }
+        if c.is_whitespace(){
+            column += 1;
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        if rest.starts_with("//") || c == '#' {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            rest = &rest[end..];
+            continue;
+        }
+        let len = if c.is_ascii_digit(){
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let number = rest[..len].parse().map_err(
+                |_| ScriptError { line, column, message: format!("'{}' ist keine Zahl", &rest[..len]) }
+            )?;
+            tokens.push((Token::Number(number), line, column));
+            len
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push((Token::Ident(rest[..len].to_owned()), line, column));
+            len
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)){
+            tokens.push((Token::Symbol(symbol), line, column));
+            symbol.len()
+        } else {
+            return Err(ScriptError { line, column, message: format!("Unbekanntes Zeichen '{c}'") });
+        };
+        column += rest[..len].chars().count();
+        rest = &rest[len..];
+    }
+    tokens.push((Token::End, line, column));
+    Ok(tokens)
+}
+
+struct Parser{
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+    depth: usize
+}
+
+impl Parser{
+    fn peek(&self) -> &Token
+    {
+        &self.tokens[self.pos].0
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, ScriptError>
+    {
+        let (_, line, column) = self.tokens[self.pos];
+        Err(ScriptError { line, column, message })
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool
+    {
+        if matches!(self.peek(), Token::Symbol(s) if *s == symbol) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), ScriptError>
+    {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            self.error(format!("Erwartet '{symbol}', gefunden {}", self.peek()))
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool
+    {
+        matches!(self.peek(), Token::Ident(name) if name == keyword)
+    }
+
+    fn enter(&mut self) -> Result<(), ScriptError>
+    {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            self.error("Das Skript ist zu tief verschachtelt".to_owned())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn rule(&mut self) -> Result<Rule, ScriptError>
+    {
+        self.enter()?;
+        let rule = if self.is_keyword("if") {
+            self.pos += 1;
+            let condition = self.expr()?;
+            let then = Box::new(self.block()?);
+            if !self.is_keyword("else") {
+                return self.error("Nach einem if-Block fehlt der else-Block".to_owned());
+            }
+            self.pos += 1;
+            let otherwise = if self.is_keyword("if") {
+                Box::new(self.rule()?)
+            } else {
+                Box::new(self.block()?)
+            };
+            Rule::If { condition, then, otherwise }
+        } else {
+            self.block()?
+        };
+        self.depth -= 1;
+        Ok(rule)
+    }
+
+    fn block(&mut self) -> Result<Rule, ScriptError>
+    {
+        self.expect("{")?;
+        if self.is_keyword("if") {
+            let rule = self.rule()?;
+            self.expect("}")?;
+            return Ok(rule);
+        }
+        let mut weights: [Option<Expr>; 4] = Default::default();
+        while !self.eat("}") {
+            let direction = match self.peek(){
+                Token::Ident(name) => Direction::from_name(name),
+                _ => None
+            };
+            let direction = match direction{
+                Some(d) => d,
+                None => return self.error(format!("Erwartet eine Richtung, gefunden {}", self.peek()))
+            };
+            if weights[direction.index()].is_some() {
+                return self.error(format!("Die Richtung {} kommt doppelt vor", self.peek()));
+            }
+            self.pos += 1;
+            self.expect(":")?;
+            weights[direction.index()] = Some(self.expr()?);
+            if !self.eat(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        if weights.iter().all(Option::is_none) {
+            return self.error("Der Block enthält keine Richtung".to_owned());
+        }
+        Ok(Rule::Weights(weights))
+    }
+
+    fn binary_level(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        next: fn(&mut Self) -> Result<Expr, ScriptError>
+    ) -> Result<Expr, ScriptError>
+    {
+        let mut left = next(self)?;
+        'outer: loop {
+            for (symbol, op) in ops{
+                if self.eat(symbol) {
+                    let right = next(self)?;
+                    left = Expr::Binary(*op, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ScriptError>
+    {
+        self.enter()?;
+        let expr = self.binary_level(&[("||", BinaryOp::Or)], Self::and)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ScriptError>
+    {
+        self.binary_level(&[("&&", BinaryOp::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ScriptError>
+    {
+        self.binary_level(
+            &[
+                ("<=", BinaryOp::LessEq),
+                (">=", BinaryOp::GreaterEq),
+                ("==", BinaryOp::Equal),
+                ("!=", BinaryOp::NotEqual),
+                ("<", BinaryOp::Less),
+                (">", BinaryOp::Greater)
+            ],
+            Self::sum
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expr, ScriptError>
+    {
+        self.binary_level(&[("+", BinaryOp::Add), ("-", BinaryOp::Sub)], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, ScriptError>
+    {
+        self.binary_level(
+            &[("*", BinaryOp::Mul), ("/", BinaryOp::Div), ("%", BinaryOp::Rem)],
+            Self::unary
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError>
+    {
+        if self.eat("-") {
+            self.enter()?;
+            let e = self.unary()?;
+            self.depth -= 1;
+            Ok(Expr::Negate(Box::new(e)))
+        } else if self.eat("!") {
+            self.enter()?;
+            let e = self.unary()?;
+            self.depth -= 1;
+            Ok(Expr::Not(Box::new(e)))
+        } else {
+            let base = self.atom()?;
+            if self.eat("^") {
+                self.enter()?;
+                let exponent = self.unary()?;
+                self.depth -= 1;
+                Ok(Expr::Binary(BinaryOp::Pow, Box::new(base), Box::new(exponent)))
+            } else {
+                Ok(base)
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, ScriptError>
+    {
+        match self.peek().clone(){
+            Token::Number(n) => {
+                self.pos += 1;
+                Ok(Expr::Number(n))
+            },
+            Token::Symbol("(") => {
+                self.pos += 1;
+                let e = self.expr()?;
+                self.expect(")")?;
+                Ok(e)
+            },
+            Token::Ident(name) => {
+                let variable = match name.as_str(){
+                    "ort" => {
+                        self.pos += 1;
+                        self.expect(".")?;
+                        let variable = match self.peek(){
+                            Token::Ident(c) if c == "x" => Variable::X,
+                            Token::Ident(c) if c == "y" => Variable::Y,
+                            _ => return self.error(format!("Erwartet 'x' oder 'y' nach 'ort.', gefunden {}", self.peek()))
+                        };
+                        self.pos += 1;
+                        return Ok(Expr::Variable(variable));
+                    },
+                    "x" => Some(Variable::X),
+                    "y" => Some(Variable::Y),
+                    "schritt" | "t" => Some(Variable::Step),
+                    "abstand" | "r" => Some(Variable::Distance),
+                    "zufall" => Some(Variable::Random),
+                    "pi" => {
+                        self.pos += 1;
+                        return Ok(Expr::Number(std::f64::consts::PI));
+                    },
+                    _ => None
+                };
+                if let Some(variable) = variable {
+                    self.pos += 1;
+                    return Ok(Expr::Variable(variable));
+                }
+                let function = match Function::from_name(&name){
+                    Some(f) => f,
+                    None => return self.error(format!("Unbekannter Name '{name}'"))
+                };
+                self.pos += 1;
+                self.expect("(")?;
+                let mut args = vec![self.expr()?];
+                while self.eat(",") {
+                    args.push(self.expr()?);
+                }
+                if args.len() != function.arity() {
+                    return self.error(
+                        format!("'{name}' braucht {} Argument(e), nicht {}", function.arity(), args.len())
+                    );
+                }
+                self.expect(")")?;
+                Ok(Expr::Call(function, args))
+            },
+            other => {
+                let mut message = format!("Erwartet eine Zahl oder einen Namen, gefunden {other}");
+                if matches!(other, Token::Symbol("{")) {
+                    message.push_str(" - fehlt eine Bedingung?");
+                }
+                self.error(message)
+            }
+        }
+    }
+}
+
+/// Ein eingelesenes Skript, das die Schritt-Wahrscheinlichkeiten festlegt.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::step_script::StepScript;
+/// let script = StepScript::parse("if ort.x > 0 { links: 1 } else { rechts: 1 }").unwrap();
+/// assert!(StepScript::parse("if x > 0 { links: 1 } else { rechts: 1 }").is_ok());
+/// assert!(StepScript::parse("{ links: 1 ").is_err());
+/// ```
+///
+/// Liefert die Regel unterwegs ungültige Gewichte, halten die Walker davor an:
+/// ```
+/// use girls_day::step_script::StepScript;
+/// use girls_day::ensemble::create_walkers;
+/// use girls_day::walk_model::{WalkModel, RadioState, YourFunctionMode};
+/// let mut model = WalkModel{
+///     radio: RadioState::YourFunction,
+///     your_function_mode: YourFunctionMode::Script,
+///     step_script: StepScript::parse("if x < 3 { rechts: 1 } else { rechts: 3 - x }"),
+///     ..WalkModel::default()
+/// };
+/// let mut walkers = create_walkers(1, 5, 10);
+/// // Bei x = 3 sind alle Gewichte 0
+/// assert_eq!(model.step_walkers(&mut walkers, 10), 3);
+/// assert_eq!(walkers[0].history.len(), 3);
+/// assert!(model.script_runtime_error.is_some());
+/// assert_eq!(model.step_walkers(&mut walkers, 10), 0);
+///
+/// // Läuft jeder Walker zufällig woanders in den Fehler, halten trotzdem alle beim frühesten an
+/// model.step_script = StepScript::parse("if x < 5 { links: 1, rechts: 1 } else { rechts: 5 - x }");
+/// model.script_runtime_error = None;
+/// let mut walkers = create_walkers(1, 20, 1000);
+/// let done = model.step_walkers(&mut walkers, 1000);
+/// assert!(done < 1000);
+/// assert!(walkers.iter().all(|walker| walker.history.len() == done as usize));
+/// assert!(walkers.iter().all(|walker| walker.ort.x <= 5));
+/// assert!(walkers.iter().any(|walker| walker.ort.x == 5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StepScript{
+    rule: Rule
+}
+
+impl StepScript{
+    pub fn parse(source: &str) -> Result<Self, ScriptError>
+    {
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0, depth: 0 };
+        let rule = parser.rule()?;
+        if *parser.peek() != Token::End {
+            return parser.error(format!("Unerwartet {} nach dem Ende der Regel", parser.peek()));
+        }
+        Ok(Self { rule })
+    }
+}
+
+impl RandomWalker{
+    /// Führt einen Schritt nach den Regeln des Skripts aus.
+    ///
+    /// Gibt das Skript ungültige Gewichte zurück, macht der Walker keinen Schritt
+    /// und die Fehlermeldung wird zurückgegeben.
+    pub fn step_from_script(&mut self, script: &StepScript) -> Result<(), String>
+    {
+        let weights = self.script_weights(script)?;
+        self.step_with_weights(&weights);
+        Ok(())
+    }
+
+    /// Die Gewichte, die das Skript für den nächsten Schritt vom aktuellen Ort aus liefert.
+    /// Der Walker bewegt sich dabei nicht, verbraucht aber die Zufallszahlen des Skripts
+    pub fn script_weights(&mut self, script: &StepScript) -> Result<[f64; 4], String>
+    {
+        let x = self.ort.x as f64;
+        let y = self.ort.y as f64;
+        let step = self.history.len() as f64;
+        let mut random = || self.get_random_number();
+        let mut ctx = Context{
+            x,
+            y,
+            step,
+            random: &mut random
+        };
+        script.rule.eval(&mut ctx)
+    }
+}
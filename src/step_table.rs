@@ -0,0 +1,386 @@
+use egui::{Color32, Sense, Stroke, Vec2, Pos2};
+use serde::{Deserialize, Serialize};
+
+use crate::random_walker::{Position, RandomWalker};
+
+/// Wann ein Schritt aus der Tabelle erlaubt ist.
+///
+/// Damit lassen sich Walker bauen, die sich je nach Ort
+/// unterschiedlich verhalten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepCondition{
+    Always,
+    XPositive,
+    XZero,
+    XNegative,
+    YPositive,
+    YZero,
+    YNegative
+}
+
+impl StepCondition{
+    pub const ALL: [StepCondition; 7] = [
+        StepCondition::Always,
+        StepCondition::XPositive,
+        StepCondition::XZero,
+        StepCondition::XNegative,
+        StepCondition::YPositive,
+        StepCondition::YZero,
+        StepCondition::YNegative
+    ];
+
+    pub fn holds(&self, pos: &Position) -> bool
+    {
+        match self{
+            StepCondition::Always => true,
+            StepCondition::XPositive => pos.x > 0,
+            StepCondition::XZero => pos.x == 0,
+            StepCondition::XNegative => pos.x < 0,
+            StepCondition::YPositive => pos.y > 0,
+            StepCondition::YZero => pos.y == 0,
+            StepCondition::YNegative => pos.y < 0
+        }
+    }
+
+    pub fn label(&self) -> &'static str
+    {
+        match self{
+            StepCondition::Always => "immer",
+            StepCondition::XPositive => "x > 0",
+            StepCondition::XZero => "x = 0",
+            StepCondition::XNegative => "x < 0",
+            StepCondition::YPositive => "y > 0",
+            StepCondition::YZero => "y = 0",
+            StepCondition::YNegative => "y < 0"
+        }
+    }
+}
+
+/// Ein möglicher Schritt: der Walker läuft um dx nach rechts und dy nach oben.
+/// Das Gewicht gibt an wie wahrscheinlich der Schritt im Vergleich zu den anderen ist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepMove{
+    pub dx: i32,
+    pub dy: i32,
+    pub weight: f64,
+    pub condition: StepCondition
+}
+
+/// Eine Liste von Schritten, die man zur Laufzeit bearbeiten kann.
+/// Das ist die Version von [`RandomWalker::your_step_function`]
+/// für die man nicht neu kompilieren muss.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepTable{
+    pub moves: Vec<StepMove>
+}
+
+impl Default for StepTable{
+    /// Die Rösselsprünge aus [`RandomWalker::your_step_function`]
+    fn default() -> Self {
+        let moves = [(1, 2), (1, -2), (-1, -2), (-1, 2), (2, 1), (-2, 1), (-2, -1), (2, -1)]
+            .into_iter()
+            .map(
+                |(dx, dy)|
+                StepMove { dx, dy, weight: 1.0, condition: StepCondition::Always }
+            ).collect();
+        Self { moves }
+    }
+}
+
+/// Alle Positionen lassen sich anhand der Vorzeichen von x und y
+/// in 9 Klassen einteilen. Innerhalb einer Klasse sind immer die selben
+/// Schritte erlaubt.
+fn sign_class_representatives() -> impl Iterator<Item = Position>
+{
+    [-1, 0, 1].into_iter()
+        .flat_map(|x| [-1, 0, 1].into_iter().map(move |y| Position { x, y }))
+}
+
+fn sign_class(pos: &Position) -> usize
+{
+    ((pos.x.signum() + 1) * 3 + pos.y.signum() + 1) as usize
+}
+
+impl StepTable{
+    /// Überprüft ob die Tabelle benutzt werden kann.
+    /// Gibt sonst eine Fehlermeldung zurück, die man anzeigen kann.
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if self.moves.is_empty(){
+            return Err("Die Tabelle braucht mindestens einen Schritt".to_owned());
+        }
+        for (i, step) in self.moves.iter().enumerate(){
+            if !(step.weight.is_finite() && step.weight > 0.0){
+                return Err(format!("Schritt {}: Das Gewicht muss größer als 0 sein", i + 1));
+            }
+        }
+        for pos in sign_class_representatives(){
+            if !self.moves.iter().any(|step| step.condition.holds(&pos)){
+                return Err(
+                    format!(
+                        "Für Orte mit x {} 0 und y {} 0 ist kein Schritt erlaubt",
+                        sign_symbol(pos.x),
+                        sign_symbol(pos.y)
+                    )
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Die erlaubten Schritte am Ort `pos` mit ihren Wahrscheinlichkeiten
+    pub fn probabilities_at(&self, pos: &Position) -> Vec<((i32, i32), f64)>
+    {
+        let allowed: Vec<&StepMove> = self.moves
+            .iter()
+            .filter(|step| step.condition.holds(pos))
+            .collect();
+        let total: f64 = allowed.iter().map(|step| step.weight).sum();
+        allowed.into_iter()
+            .map(|step| ((step.dx, step.dy), step.weight / total))
+            .collect()
+    }
+
+    pub fn compile(&self) -> Result<CompiledStepTable, String>
+    {
+        self.validate()?;
+        let classes = sign_class_representatives()
+            .map(
+                |pos|
+                {
+                    let (moves, weights): (Vec<_>, Vec<_>) = self.moves
+                        .iter()
+                        .filter(|step| step.condition.holds(&pos))
+                        .map(|step| ((step.dx, step.dy), step.weight))
+                        .unzip();
+                    let alias = AliasTable::new(&weights);
+                    (moves, alias)
+                }
+            ).collect();
+        Ok(CompiledStepTable { classes })
+    }
+
+    /// Zeigt den Editor für die Tabelle an.
+    /// Gibt true zurück, wenn sich die Tabelle geändert hat
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool
+    {
+        let mut changed = false;
+        let mut remove = None;
+        ui.horizontal(
+            |ui|
+            {
+                egui::Grid::new("step_table_grid")
+                    .striped(true)
+                    .show(
+                        ui,
+                        |ui|
+                        {
+                            ui.label("dx");
+                            ui.label("dy");
+                            ui.label("Gewicht");
+                            ui.label("Wann");
+                            ui.end_row();
+                            for (i, step) in self.moves.iter_mut().enumerate(){
+                                changed |= ui.add(egui::DragValue::new(&mut step.dx).speed(0.1)).changed();
+                                changed |= ui.add(egui::DragValue::new(&mut step.dy).speed(0.1)).changed();
+                                changed |= ui.add(
+                                    egui::DragValue::new(&mut step.weight)
+                                        .speed(0.01)
+                                        .clamp_range(0.0..=100.0)
+                                ).changed();
+                                egui::ComboBox::from_id_source(("step_condition", i))
+                                    .selected_text(step.condition.label())
+                                    .show_ui(
+                                        ui,
+                                        |ui|
+                                        {
+                                            for condition in StepCondition::ALL{
+                                                changed |= ui.selectable_value(&mut step.condition, condition, condition.label())
+                                                    .changed();
+                                            }
+                                        }
+                                    );
+                                if ui.button("🗑").on_hover_text("Schritt entfernen").clicked(){
+                                    remove = Some(i);
+                                }
+                                ui.end_row();
+                            }
+                        }
+                    );
+                self.star_diagram(ui);
+            }
+        );
+        if let Some(i) = remove{
+            self.moves.remove(i);
+            changed = true;
+        }
+        ui.horizontal(
+            |ui|
+            {
+                if ui.button("+ Schritt").clicked(){
+                    self.moves.push(StepMove { dx: 1, dy: 0, weight: 1.0, condition: StepCondition::Always });
+                    changed = true;
+                }
+                if ui.button("Zurücksetzen").on_hover_text("Stellt die Rösselsprünge wieder her").clicked(){
+                    *self = Self::default();
+                    changed = true;
+                }
+            }
+        );
+        if let Err(error) = self.validate(){
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        changed
+    }
+
+    /// Zeichnet alle Schritte als Pfeile vom Mittelpunkt aus.
+    /// Je dicker die Linie, desto größer das Gewicht.
+    /// Schritte die nur manchmal erlaubt sind werden heller gezeichnet.
+    fn star_diagram(&self, ui: &mut egui::Ui)
+    {
+        let size = 120.0;
+        let (response, painter) = ui.allocate_painter(Vec2 { x: size, y: size }, Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 4.0, Color32::BLACK);
+        let center = rect.center();
+
+        let max_len = self.moves
+            .iter()
+            .map(|step| step.dx.abs().max(step.dy.abs()))
+            .max()
+            .unwrap_or(1)
+            .max(1) as f32;
+        let max_weight = self.moves
+            .iter()
+            .map(|step| step.weight)
+            .fold(0.0, f64::max);
+        let scale = (size * 0.45) / max_len;
+
+        for step in self.moves.iter(){
+            let width = if max_weight > 0.0 {
+                (4.0 * step.weight / max_weight) as f32
+            } else {
+                0.0
+            }.max(0.5);
+            let color = match step.condition{
+                StepCondition::Always => Color32::from_rgb(254, 42, 42),
+                _ => Color32::from_rgb(255, 170, 120)
+            };
+            // y zeigt auf dem Bildschirm nach unten
+            let end = Pos2{
+                x: center.x + scale * step.dx as f32,
+                y: center.y - scale * step.dy as f32
+            };
+            painter.line_segment([center, end], Stroke::new(width, color));
+            painter.circle_filled(end, width.max(2.0), color);
+        }
+        painter.circle_filled(center, 2.0, Color32::WHITE);
+    }
+}
+
+fn sign_symbol(v: i32) -> &'static str
+{
+    match v.signum(){
+        1 => ">",
+        0 => "=",
+        _ => "<"
+    }
+}
+
+/// Alias-Tabelle nach Walker/Vose:
+/// Damit kann man aus beliebig vielen gewichteten Möglichkeiten
+/// mit nur einer Zufallszahl und konstantem Aufwand ziehen.
+#[derive(Debug, Clone)]
+pub struct AliasTable{
+    prob: Vec<f64>,
+    alias: Vec<usize>
+}
+
+impl AliasTable{
+    /// Die Gewichte müssen positiv sein und es muss mindestens eins geben
+    pub fn new(weights: &[f64]) -> Self
+    {
+        let n = weights.len();
+        let total: f64 = weights.iter().sum();
+        let mut prob: Vec<f64> = weights.iter()
+            .map(|w| w * n as f64 / total)
+            .collect();
+        let mut alias: Vec<usize> = (0..n).collect();
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..n)
+            .partition(|&i| prob[i] < 1.0);
+
+        while let (Some(s), Some(l)) = (small.pop(), large.pop()){
+            alias[s] = l;
+            prob[l] -= 1.0 - prob[s];
+            if prob[l] < 1.0 {
+                small.push(l);
+            } else {
+                large.push(l);
+            }
+        }
+        // Rundungsfehler: was übrig bleibt wird immer angenommen
+        for i in small.into_iter().chain(large){
+            prob[i] = 1.0;
+        }
+        Self { prob, alias }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.prob.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.prob.is_empty()
+    }
+
+    /// `random` muss im Intervall [0,1) liegen.
+    /// Der ganzzahlige Anteil von random·n wählt die Spalte,
+    /// der Rest entscheidet zwischen der Spalte und ihrem Alias
+    pub fn sample(&self, random: f64) -> usize
+    {
+        let scaled = random * self.len() as f64;
+        let column = (scaled as usize).min(self.len() - 1);
+        let rest = scaled - column as f64;
+        if rest < self.prob[column] {
+            column
+        } else {
+            self.alias[column]
+        }
+    }
+}
+
+/// Die Schritttabelle in einer Form, aus der schnell gezogen werden kann.
+/// Für jede der 9 Vorzeichen-Klassen von (x, y) gibt es eine eigene Alias-Tabelle.
+#[derive(Debug, Clone)]
+pub struct CompiledStepTable{
+    classes: Vec<(Vec<(i32, i32)>, AliasTable)>
+}
+
+impl CompiledStepTable{
+    pub fn moves_at(&self, pos: &Position) -> &[(i32, i32)]
+    {
+        &self.classes[sign_class(pos)].0
+    }
+
+    pub fn sample(&self, pos: &Position, random: f64) -> (i32, i32)
+    {
+        let (moves, alias) = &self.classes[sign_class(pos)];
+        moves[alias.sample(random)]
+    }
+}
+
+impl RandomWalker{
+    /// Führt einen Schritt aus, der aus der Schritttabelle gezogen wird
+    pub fn step_from_table(&mut self, table: &CompiledStepTable)
+    {
+        let alter_ort = self.ort.clone();
+        let random = self.get_random_number();
+        let (dx, dy) = table.sample(&alter_ort, random);
+        self.ort.x += dx;
+        self.ort.y += dy;
+        self.history.push(alter_ort);
+    }
+}
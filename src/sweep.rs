@@ -0,0 +1,308 @@
+use egui::plot::*;
+
+use crate::ensemble::{Batch, BatchProgress};
+use crate::power_law::{fit_power_law, PowerLawFit};
+use crate::random_walker::Welford;
+use crate::walk_model::{RadioState, WalkModel};
+
+/// Welcher Parameter des Modells verändert wird
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SweepParameter{
+    StrengthOfBias,
+    Coupling,
+    Repulsion
+}
+
+impl SweepParameter{
+    pub const ALL: [SweepParameter; 3] = [
+        SweepParameter::StrengthOfBias,
+        SweepParameter::Coupling,
+        SweepParameter::Repulsion
+    ];
+
+    pub fn label(&self) -> &'static str
+    {
+        match self{
+            SweepParameter::StrengthOfBias => "Bias",
+            SweepParameter::Coupling => "Kopplung",
+            SweepParameter::Repulsion => "Abstoßung g"
+        }
+    }
+
+    /// Der erlaubte Bereich, wie bei den Schiebereglern
+    pub fn range(&self) -> (f64, f64)
+    {
+        match self{
+            SweepParameter::StrengthOfBias => (0.0, 0.5),
+            SweepParameter::Coupling => (-1.0, 1.0),
+            SweepParameter::Repulsion => (0.0, 10.0)
+        }
+    }
+
+    pub fn set(&self, model: &mut WalkModel, value: f64)
+    {
+        match self{
+            SweepParameter::StrengthOfBias => model.strength_of_bias = value,
+            SweepParameter::Coupling => model.coupling = value,
+            SweepParameter::Repulsion => model.repulsion = value
+        }
+    }
+
+    /// Ob die Schrittregel den Parameter überhaupt benutzt
+    pub fn used_by(&self, radio: RadioState) -> bool
+    {
+        match self{
+            SweepParameter::StrengthOfBias => matches!(
+                radio,
+                RadioState::BiasedAwayFromOrigin
+                    | RadioState::BiasedTowardsOrigin
+                    | RadioState::RadialBiasAwayFromOrigin
+                    | RadioState::RadialBiasTowardsOrigin
+                    | RadioState::YourFunction
+            ),
+            SweepParameter::Coupling => radio == RadioState::Interacting,
+            SweepParameter::Repulsion => radio == RadioState::TrueSelfAvoiding
+        }
+    }
+}
+
+/// Was gegen den Parameter aufgetragen wird
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SweepResult{
+    FinalDistance,
+    Exponent
+}
+
+/// Das Ergebnis für einen Wert des Parameters
+#[derive(Debug, Clone, Copy)]
+pub struct SweepPoint{
+    pub parameter: f64,
+    /// Der Abstand aller Walker beim letzten Schritt
+    pub final_distance: Welford,
+    /// Potenzgesetz a·t^b für den mittleren Abstand ab einem Zehntel der Schritte
+    pub fit: Option<PowerLawFit>
+}
+
+/// Der Wert des Parameters, der gerade simuliert wird
+#[derive(Debug, Clone)]
+struct SweepBatch{
+    parameter: f64,
+    model: WalkModel,
+    batch: Batch
+}
+
+/// Lässt die Walker für viele Werte eines Parameters laufen und trägt
+/// den mittleren Abstand am Ende oder den Exponenten gegen den Parameter auf.
+///
+/// Alle Werte verwenden den gleichen seed - dadurch ist die Kurve viel glatter,
+/// als sie es mit unabhängigen Läufen wäre.
+/// Gerechnet wird nebenbei, pro Bild nur ein kleines Stück
+#[derive(Debug, Clone)]
+pub struct Sweep{
+    pub parameter: SweepParameter,
+    pub from: f64,
+    pub to: f64,
+    pub values: usize,
+    pub steps: usize,
+    pub shown: SweepResult,
+    pending: Vec<f64>,
+    batch: Option<SweepBatch>,
+    /// Das Modell, der seed und die Anzahl an Walkern beim Start
+    setup: Option<(WalkModel, u64, usize)>,
+    pub results: Vec<SweepPoint>
+}
+
+impl Default for Sweep{
+    fn default() -> Self {
+        Self{
+            parameter: SweepParameter::StrengthOfBias,
+            from: 0.0,
+            to: 0.2,
+            values: 21,
+            steps: 1000,
+            shown: SweepResult::FinalDistance,
+            pending: Vec::new(),
+            batch: None,
+            setup: None,
+            results: Vec::new()
+        }
+    }
+}
+
+impl Sweep{
+    pub fn is_busy(&self) -> bool
+    {
+        self.batch.is_some() || !self.pending.is_empty()
+    }
+
+    pub fn stop(&mut self)
+    {
+        self.pending.clear();
+        self.batch = None;
+    }
+
+    /// Verwirft alte Ergebnisse und merkt sich alle Werte des Parameters,
+    /// die simuliert werden sollen. Spätere Änderungen am Modell
+    /// haben keinen Einfluss mehr auf diesen Durchlauf
+    pub fn start(&mut self, model: &WalkModel, seed: u64, num_of_walkers: usize)
+    {
+        self.stop();
+        self.results.clear();
+        self.setup = Some((model.clone(), seed, num_of_walkers));
+        let n = self.values.max(1);
+        self.pending = (0..n)
+            .rev()
+            .map(
+                |i|
+                {
+                    if n == 1 {
+                        self.from
+                    } else {
+                        self.from + (self.to - self.from) * i as f64 / (n - 1) as f64
+                    }
+                }
+            ).collect();
+    }
+
+    /// Rechnet am aktuellen Wert des Parameters weiter
+    pub fn run_pending(&mut self)
+    {
+        if self.batch.is_none() {
+            let (Some(parameter), Some((model, seed, num_of_walkers))) = (self.pending.pop(), &self.setup) else {
+                return;
+            };
+            let (seed, num_of_walkers) = (*seed, *num_of_walkers);
+            let mut model = model.clone();
+            self.parameter.set(&mut model, parameter);
+            self.batch = Some(
+                SweepBatch{
+                    parameter,
+                    model,
+                    batch: Batch::new(seed, num_of_walkers, self.steps)
+                }
+            );
+        }
+        let SweepBatch { model, batch, .. } = self.batch.as_mut().unwrap();
+        match batch.advance(model) {
+            BatchProgress::Running => {},
+            BatchProgress::Failed => self.stop(),
+            BatchProgress::Finished => {
+                let SweepBatch { parameter, batch, .. } = self.batch.take().unwrap();
+                let distance = &batch.stats.distance;
+                let steps_per_entry = batch.stats.steps_per_entry();
+                let fit_start = (distance.len() / 10).max(1);
+                let points: Vec<PlotPoint> = distance[fit_start..]
+                    .iter()
+                    .zip(fit_start..)
+                    .map(|(w, i)| PlotPoint { x: (i * steps_per_entry) as f64, y: w.mean() })
+                    .collect();
+                self.results.push(
+                    SweepPoint{
+                        parameter,
+                        final_distance: distance.last().copied().unwrap_or_default(),
+                        fit: fit_power_law(&points)
+                    }
+                );
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &WalkModel, seed: u64, num_of_walkers: usize)
+    {
+        let busy = self.is_busy();
+        ui.add_enabled_ui(
+            !busy,
+            |ui|
+            {
+                ui.horizontal(
+                    |ui|
+                    {
+                        for parameter in SweepParameter::ALL{
+                            if ui.selectable_value(&mut self.parameter, parameter, parameter.label()).changed(){
+                                (self.from, self.to) = parameter.range();
+                            }
+                        }
+                        let (min, max) = self.parameter.range();
+                        ui.label("von");
+                        ui.add(egui::DragValue::new(&mut self.from).clamp_range(min..=max).speed(0.01));
+                        ui.label("bis");
+                        ui.add(egui::DragValue::new(&mut self.to).clamp_range(min..=max).speed(0.01));
+                        ui.label("Werte");
+                        ui.add(egui::DragValue::new(&mut self.values).clamp_range(1..=200));
+                        ui.label("Schritte");
+                        ui.add(egui::DragValue::new(&mut self.steps).clamp_range(10..=500000).speed(10.0));
+                    }
+                );
+            }
+        );
+        ui.horizontal(
+            |ui|
+            {
+                let button = egui::Button::new(format!("Starten mit je {num_of_walkers} Walkern"));
+                if ui.add_enabled(!busy, button)
+                    .on_hover_text("Simuliert die Walker mit den aktuellen Einstellungen für jeden Wert des Parameters")
+                    .clicked()
+                {
+                    self.start(model, seed, num_of_walkers);
+                }
+                if busy {
+                    ui.spinner();
+                    let total = self.results.len() + self.pending.len() + 1;
+                    ui.label(format!("Wert {} von {total}", self.results.len() + 1));
+                    if ui.button("Stopp").clicked(){
+                        self.stop();
+                    }
+                }
+                ui.separator();
+                ui.selectable_value(&mut self.shown, SweepResult::FinalDistance, "Abstand am Ende");
+                ui.selectable_value(&mut self.shown, SweepResult::Exponent, "Exponent b")
+                    .on_hover_text("Der Exponent b aus dem Fit a·t^b an den mittleren Abstand, ab einem Zehntel der Schritte. Für den normalen Random Walk ist b = 1/2");
+            }
+        );
+        if !self.parameter.used_by(model.radio) {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("'{}' benutzt den Parameter '{}' nicht", model.radio.label(), self.parameter.label())
+            );
+        }
+
+        let points: Vec<PlotPoint> = self.results.iter()
+            .filter_map(
+                |point|
+                {
+                    let y = match self.shown{
+                        SweepResult::FinalDistance => Some(point.final_distance.mean()),
+                        SweepResult::Exponent => point.fit.map(|fit| fit.exponent)
+                    };
+                    y.map(|y| PlotPoint { x: point.parameter, y })
+                }
+            ).collect();
+        let name = match self.shown{
+            SweepResult::FinalDistance => format!("Abstand nach {} Schritten", self.steps),
+            SweepResult::Exponent => "Exponent b".to_owned()
+        };
+        let x_label = self.parameter.label();
+        Plot::new("sweep_plot")
+            .legend(Legend::default())
+            .label_formatter(move |name, p| format!("{name}\n{x_label} = {:.4}\ny = {:.4}", p.x, p.y))
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    if self.shown == SweepResult::FinalDistance {
+                        for point in &self.results{
+                            let w = point.final_distance;
+                            plot_ui.line(
+                                Line::new(PlotPoints::Owned(vec![
+                                    PlotPoint { x: point.parameter, y: w.mean() - w.std_error() },
+                                    PlotPoint { x: point.parameter, y: w.mean() + w.std_error() }
+                                ])).name("± Standardfehler")
+                            );
+                        }
+                    }
+                    plot_ui.line(Line::new(PlotPoints::Owned(points.clone())).name(&name));
+                    plot_ui.points(Points::new(PlotPoints::Owned(points)).name(&name).radius(3.0));
+                }
+            );
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use crate::random_walker::Position;
+
+/// Kantenlänge einer Kachel, 8 · 8 Felder passen in ein u64
+const TILE: i32 = 8;
+
+/// Die Kachel eines Feldes und das Bit des Feldes darin
+fn tile_and_bit(pos: &Position) -> ((i32, i32), u64)
+{
+    let tile = (pos.x.div_euclid(TILE), pos.y.div_euclid(TILE));
+    let bit = pos.x.rem_euclid(TILE) + TILE * pos.y.rem_euclid(TILE);
+    (tile, 1 << bit)
+}
+
+/// Die Felder, auf denen ein Walker schon war.
+///
+/// Ein Walker besucht meistens viele benachbarte Felder, daher wird nicht jedes
+/// Feld einzeln gespeichert, sondern Kacheln von 8 × 8 Feldern mit einem Bit pro Feld.
+/// Eine Kachel braucht in der HashMap 17 Byte und etwas Platz für das Wachsen.
+/// Bei einem normalen Random Walk sind das ungefähr 0,6 Byte pro besuchtem Feld,
+/// in einem `HashSet<Position>` wären es 10 bis 20 Byte.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::visited_sites::VisitedSites;
+/// use girls_day::random_walker::Position;
+/// let mut visited = VisitedSites::default();
+/// assert!(visited.insert(&Position { x: 0, y: 0 }));
+/// assert!(visited.insert(&Position { x: -1, y: 7 }));
+/// assert!(visited.insert(&Position { x: 8, y: -9 }));
+/// assert!(!visited.insert(&Position { x: -1, y: 7 }));
+/// assert_eq!(visited.len(), 3);
+/// assert!(visited.contains(&Position { x: 8, y: -9 }));
+/// assert!(!visited.contains(&Position { x: 9, y: -9 }));
+/// let mut sites: Vec<(i32, i32)> = visited.iter().map(|pos| (pos.x, pos.y)).collect();
+/// sites.sort();
+/// assert_eq!(sites, [(-1, 7), (0, 0), (8, -9)]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VisitedSites{
+    tiles: HashMap<(i32, i32), u64>,
+    len: usize
+}
+
+impl VisitedSites{
+    /// Markiert `pos` als besucht. true, wenn der Walker vorher noch nicht dort war
+    pub fn insert(&mut self, pos: &Position) -> bool
+    {
+        let (tile, bit) = tile_and_bit(pos);
+        let bits = self.tiles.entry(tile).or_insert(0);
+        let new = *bits & bit == 0;
+        *bits |= bit;
+        self.len += new as usize;
+        new
+    }
+
+    pub fn contains(&self, pos: &Position) -> bool
+    {
+        let (tile, bit) = tile_and_bit(pos);
+        self.tiles.get(&tile).map_or(false, |bits| bits & bit != 0)
+    }
+
+    /// Wie viele verschiedene Felder besucht wurden
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+
+    /// Alle besuchten Felder, in keiner bestimmten Reihenfolge
+    pub fn iter(&self) -> impl Iterator<Item = Position> + '_
+    {
+        self.tiles.iter()
+            .flat_map(
+                |(&(tile_x, tile_y), &bits)|
+                {
+                    (0..TILE * TILE)
+                        .filter(move |bit| bits & (1 << bit) != 0)
+                        .map(
+                            move |bit|
+                            Position{
+                                x: tile_x * TILE + bit % TILE,
+                                y: tile_y * TILE + bit / TILE
+                            }
+                        )
+                }
+            )
+    }
+}
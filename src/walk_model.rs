@@ -0,0 +1,462 @@
+use rayon::prelude::*;
+
+use crate::random_walker::{
+    RandomWalker,
+    HistoryMark,
+    AverageDistance,
+    Position,
+    MAX_RECORDS_PER_CHUNK,
+    DIRECTIONS,
+    step_interacting_walkers,
+    weights_along,
+    axis_bias_probabilities
+};
+use crate::step_table::{StepTable, CompiledStepTable};
+use crate::step_script::{StepScript, ScriptError, EXAMPLE_SCRIPT, SCRIPT_HELP};
+
+const STEP_TABLE_KEY: &str = "step_table";
+const STEP_SCRIPT_KEY: &str = "step_script";
+
+/// Die möglichen Schritte (dx, dy) mit ihren Wahrscheinlichkeiten
+pub type Transitions = Vec<((i32, i32), f64)>;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum RadioState{
+    NoBias,
+    BiasedTowardsOrigin,
+    BiasedAwayFromOrigin,
+    RadialBiasTowardsOrigin,
+    RadialBiasAwayFromOrigin,
+    Directional,
+    Interacting,
+    TrueSelfAvoiding,
+    YourFunction
+}
+
+impl RadioState{
+    /// Kurzer Name der Schrittregel, z.B. für Legenden
+    pub fn label(&self) -> &'static str
+    {
+        match self{
+            RadioState::NoBias => "Normaler Random Walk",
+            RadioState::BiasedTowardsOrigin => "Bias - zum Ursprung hin",
+            RadioState::BiasedAwayFromOrigin => "Bias - weg vom Ursprung",
+            RadioState::RadialBiasTowardsOrigin => "Radialer Bias - zum Ursprung hin",
+            RadioState::RadialBiasAwayFromOrigin => "Radialer Bias - weg vom Ursprung",
+            RadioState::Directional => "Feste Richtungen",
+            RadioState::Interacting => "Wechselwirkende Walker",
+            RadioState::TrueSelfAvoiding => "Selbstabstoßend",
+            RadioState::YourFunction => "Deine Funktion"
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum YourFunctionMode{
+    Table,
+    Script,
+    /// [`RandomWalker::your_step_function`], zum selbst Programmieren
+    Code
+}
+
+/// Die Regel nach der die Walker laufen, zusammen mit all ihren Parametern
+#[derive(Clone, Debug)]
+pub struct WalkModel{
+    pub radio: RadioState,
+    pub strength_of_bias: f64,
+    pub direction_probabilities: [f64; 4],
+    pub coupling: f64,
+    pub repulsion: f64,
+    pub one_dimensional: bool,
+    pub step_table: StepTable,
+    pub compiled_step_table: Result<CompiledStepTable, String>,
+    pub your_function_mode: YourFunctionMode,
+    pub step_script_source: String,
+    pub step_script: Result<StepScript, ScriptError>,
+    pub script_runtime_error: Option<String>,
+    /// Ist der Kasten aktiv, können die Walker ihn nicht verlassen
+    pub box_enabled: bool,
+    /// Der Kasten reicht von -box_half_width bis box_half_width, in x und in y
+    pub box_half_width: i32
+}
+
+impl Default for WalkModel{
+    fn default() -> Self {
+        Self{
+            radio: RadioState::NoBias,
+            strength_of_bias: 0.1,
+            direction_probabilities: [0.25; 4],
+            coupling: 0.3,
+            repulsion: 1.0,
+            one_dimensional: false,
+            step_table: StepTable::default(),
+            compiled_step_table: StepTable::default().compile(),
+            your_function_mode: YourFunctionMode::Table,
+            step_script_source: EXAMPLE_SCRIPT.to_owned(),
+            step_script: StepScript::parse(EXAMPLE_SCRIPT),
+            script_runtime_error: None,
+            box_enabled: false,
+            box_half_width: 10
+        }
+    }
+}
+
+/// Lässt jeden Walker `steps` mal die Schrittfunktion ausführen.
+/// Mit `boundary` wird nach jedem Schritt darauf geachtet,
+/// dass der Walker im Kasten bleibt
+fn for_each_walker(
+    walkers: &mut [RandomWalker], 
+    steps: u64, 
+    boundary: Option<i32>,
+    step_fun: impl Fn(&mut RandomWalker) + Sync + Send
+)
+{
+    walkers.par_iter_mut()
+        .for_each(
+            |walker|
+            {
+                for _ in 0..steps{
+                    step_fun(walker);
+                    if let Some(half_width) = boundary {
+                        walker.keep_inside_box(half_width);
+                    }
+                }
+            }
+        );
+}
+
+impl WalkModel{
+    /// Lädt die Schritttabelle und die Regel von "Deine Funktion" aus dem Speicher
+    pub fn load(&mut self, storage: &dyn eframe::Storage)
+    {
+        if let Some(step_table) = eframe::get_value::<StepTable>(storage, STEP_TABLE_KEY) {
+            self.compiled_step_table = step_table.compile();
+            self.step_table = step_table;
+        }
+        if let Some(source) = eframe::get_value::<String>(storage, STEP_SCRIPT_KEY) {
+            self.step_script = StepScript::parse(&source);
+            self.step_script_source = source;
+        }
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage)
+    {
+        eframe::set_value(storage, STEP_TABLE_KEY, &self.step_table);
+        eframe::set_value(storage, STEP_SCRIPT_KEY, &self.step_script_source);
+    }
+
+    /// Die halbe Breite des Kastens, falls er aktiv ist
+    pub fn boundary(&self) -> Option<i32>
+    {
+        self.box_enabled.then_some(self.box_half_width)
+    }
+
+    /// Die möglichen Schritte vom Ort `pos` aus mit ihren Wahrscheinlichkeiten -
+    /// ohne den Kasten.
+    /// 
+    /// Gibt einen Fehler zurück, wenn die Wahrscheinlichkeiten nicht nur vom Ort abhängen,
+    /// z.B. beim selbstabstoßenden Walker, oder wenn die Tabelle ungültig ist
+    pub fn transition_probabilities(&self, pos: &Position) -> Result<Transitions, String>
+    {
+        let normalized = |weights: [f64; 4]| {
+            let total: f64 = weights.iter().sum();
+            DIRECTIONS.into_iter()
+                .zip(weights)
+                .map(|(d, w)| (d, w / total))
+                .collect()
+        };
+        let radial = |c: f64| normalized(weights_along((pos.x as f64, pos.y as f64), c));
+        match self.radio{
+            RadioState::NoBias => Ok(normalized([1.0; 4])),
+            RadioState::BiasedAwayFromOrigin => Ok(normalized(axis_bias_probabilities(pos, self.strength_of_bias))),
+            RadioState::BiasedTowardsOrigin => Ok(normalized(axis_bias_probabilities(pos, -self.strength_of_bias))),
+            RadioState::RadialBiasAwayFromOrigin => Ok(radial(2.0 * self.strength_of_bias)),
+            RadioState::RadialBiasTowardsOrigin => Ok(radial(-2.0 * self.strength_of_bias)),
+            RadioState::Directional => Ok(normalized(self.direction_probabilities)),
+            RadioState::YourFunction if self.your_function_mode == YourFunctionMode::Table => {
+                self.step_table.validate()?;
+                Ok(self.step_table.probabilities_at(pos))
+            },
+            RadioState::YourFunction if self.your_function_mode == YourFunctionMode::Script => {
+                Err("Eine Regel kann Zufallszahlen benutzen und lässt sich daher nicht exakt aufzählen".to_owned())
+            },
+            RadioState::YourFunction => Err("Eigener Code kann Zufallszahlen beliebig benutzen und lässt sich daher nicht exakt aufzählen".to_owned()),
+            RadioState::Interacting => Err("Wechselwirkende Walker hängen voneinander ab".to_owned()),
+            RadioState::TrueSelfAvoiding => Err("Der selbstabstoßende Walker hängt von seiner Vergangenheit ab".to_owned())
+        }
+    }
+
+    /// Lässt alle Walker `steps` Schritte machen.
+    /// 
+    /// Gibt die Anzahl der tatsächlich gemachten Schritte zurück. Die ist 0,
+    /// wenn die Tabelle oder Regel von "Deine Funktion" ungültig ist -
+    /// dann bleiben die Walker stehen. Liefert die Regel unterwegs ungültige Gewichte,
+    /// halten alle Walker vor diesem Schritt an
+    pub fn step_walkers(&mut self, walkers: &mut [RandomWalker], steps: u64) -> u64
+    {
+        let strength_of_bias = self.strength_of_bias;
+        let boundary = self.boundary();
+        match self.radio{
+            RadioState::NoBias => {
+                for_each_walker(walkers, steps, boundary, RandomWalker::random_step);
+            },
+            RadioState::Directional => {
+                let probabilities = self.direction_probabilities;
+                for_each_walker(walkers, steps, boundary, |walker| walker.random_step_directional(&probabilities));
+            },
+            RadioState::Interacting => {
+                for _ in 0..steps{
+                    step_interacting_walkers(walkers, self.coupling);
+                    if let Some(half_width) = boundary {
+                        walkers.par_iter_mut()
+                            .for_each(|walker| walker.keep_inside_box(half_width));
+                    }
+                }
+            },
+            RadioState::YourFunction if self.your_function_mode == YourFunctionMode::Script => {
+                // Ist die Regel ungültig, bleiben die Walker stehen
+                match &self.step_script{
+                    Ok(script) if self.script_runtime_error.is_none() => {
+                        // Jeder Walker läuft für sich, bis die Regel bei ihm einen Fehler liefert.
+                        // Danach werden alle auf den frühesten Fehler zurückgesetzt,
+                        // damit keiner einen Schritt weiter ist als die anderen
+                        let runs: Vec<(u64, Option<String>, usize, HistoryMark)> = walkers.par_iter_mut()
+                            .map(
+                                |walker|
+                                {
+                                    let start = walker.history.len();
+                                    let mark = walker.history.mark();
+                                    for done in 0..steps{
+                                        if let Err(error) = walker.step_from_script(script) {
+                                            return (done, Some(error), start, mark);
+                                        }
+                                        if let Some(half_width) = boundary {
+                                            walker.keep_inside_box(half_width);
+                                        }
+                                    }
+                                    (steps, None, start, mark)
+                                }
+                            ).collect();
+                        let Some((done, error, _, _)) = runs.iter().min_by_key(|(done, ..)| *done) else {
+                            return steps;
+                        };
+                        if error.is_some() {
+                            self.script_runtime_error = error.clone();
+                            let done = *done;
+                            walkers.par_iter_mut()
+                                .zip(&runs)
+                                .for_each(|(walker, (_, _, start, mark))| walker.truncate(start + done as usize, mark));
+                            return done;
+                        }
+                    },
+                    _ => {
+                        return 0;
+                    }
+                }
+            },
+            RadioState::YourFunction if self.your_function_mode == YourFunctionMode::Code => {
+                for_each_walker(walkers, steps, boundary, |walker| walker.your_step_function(strength_of_bias));
+            },
+            RadioState::YourFunction => {
+                // Ist die Tabelle ungültig, bleiben die Walker stehen
+                match &self.compiled_step_table{
+                    Ok(table) => for_each_walker(walkers, steps, boundary, |walker| walker.step_from_table(table)),
+                    Err(_) => return 0
+                }
+            },
+            RadioState::BiasedAwayFromOrigin => {
+                for_each_walker(walkers, steps, boundary, |walker| walker.random_step_biased_away(strength_of_bias));
+            },
+            RadioState::BiasedTowardsOrigin => {
+                for_each_walker(walkers, steps, boundary, |walker| walker.random_step_biased_to_origin(strength_of_bias));
+            },
+            RadioState::RadialBiasAwayFromOrigin => {
+                for_each_walker(walkers, steps, boundary, |walker| walker.random_step_radial_bias_away(strength_of_bias));
+            },
+            RadioState::RadialBiasTowardsOrigin => {
+                for_each_walker(walkers, steps, boundary, |walker| walker.random_step_radial_bias_to_origin(strength_of_bias));
+            },
+            RadioState::TrueSelfAvoiding => {
+                let repulsion = self.repulsion;
+                if self.one_dimensional {
+                    for_each_walker(walkers, steps, boundary, |walker| walker.random_step_true_self_avoiding_1d(repulsion));
+                } else {
+                    for_each_walker(walkers, steps, boundary, |walker| walker.random_step_true_self_avoiding(repulsion));
+                }
+            }
+        }
+        steps
+    }
+
+    /// Lässt alle Walker `steps` Schritte machen und nimmt die neuen Schritte in `average` auf.
+    ///
+    /// Die Schritte werden in Stücken gemacht, nach jedem Stück vergessen die Walker ohne
+    /// Spur ihre Messwerte wieder. So liegen auch bei zehntausenden Walkern höchstens
+    /// [`MAX_RECORDS_PER_CHUNK`] Messwerte gleichzeitig im Speicher.
+    /// Gibt wie [`WalkModel::step_walkers`] die Anzahl der gemachten Schritte zurück
+    pub fn step_and_measure(&mut self, walkers: &mut [RandomWalker], steps: u64, average: &mut AverageDistance) -> u64
+    {
+        let chunk = (MAX_RECORDS_PER_CHUNK / walkers.len()).max(1) as u64;
+        let mut done = 0;
+        while done < steps {
+            let requested = chunk.min(steps - done);
+            let stepped = self.step_walkers(walkers, requested);
+            if stepped > 0 {
+                average.update_on_step_of_walkers(stepped as usize, walkers);
+                walkers.par_iter_mut()
+                    .for_each(|walker| walker.history.forget_records());
+            }
+            done += stepped;
+            if stepped < requested {
+                break;
+            }
+        }
+        done
+    }
+
+    /// Auswahl der Schrittregel und ihrer Parameter
+    pub fn ui(&mut self, ui: &mut egui::Ui)
+    {
+        let Self {
+            radio,
+            strength_of_bias,
+            direction_probabilities,
+            coupling,
+            repulsion,
+            one_dimensional,
+            step_table,
+            compiled_step_table,
+            your_function_mode,
+            step_script_source,
+            step_script,
+            script_runtime_error,
+            box_enabled,
+            box_half_width
+        } = self;
+
+        ui.radio_value(radio, RadioState::NoBias, "Normaler Random Walk")
+            .on_hover_text("Ist diese Option ausgewählt wird ein 'normaler' random walk ausgeführt. D.h. die Wahrscheinlichkeit für den Walker nach oben, unten, rechts oder links zu gehen ist identisch.");
+        ui.radio_value(radio, RadioState::BiasedAwayFromOrigin, "Bias - weg vom Ursprung")
+            .on_hover_text("Ist diese Option ausgewählt wird ein gebiaster random walk ausgeführt. Schritte die vom ursprung wegführen werden preferiert. Wie stark diese Präferenz ist hängt vom Bias ab.");
+        ui.radio_value(radio, RadioState::BiasedTowardsOrigin, "Bias - zum Ursprung hin")
+            .on_hover_text("Ist diese Option ausgewählt wird ein gebiaster random walk ausgeführt. Schritte die zum ursprung hinführen werden preferiert. Wie stark diese Präferenz ist hängt vom Bias ab.");
+        ui.radio_value(radio, RadioState::RadialBiasAwayFromOrigin, "Radialer Bias - weg vom Ursprung")
+            .on_hover_text("Wie 'Bias - weg vom Ursprung', aber der Bias wirkt entlang der tatsächlichen Richtung vom Ursprung zum Walker und nicht getrennt für jede Achse.");
+        ui.radio_value(radio, RadioState::RadialBiasTowardsOrigin, "Radialer Bias - zum Ursprung hin")
+            .on_hover_text("Wie 'Bias - zum Ursprung hin', aber der Bias wirkt entlang der tatsächlichen Richtung vom Walker zum Ursprung und nicht getrennt für jede Achse.");
+        ui.radio_value(radio, RadioState::Directional, "Feste Richtungen")
+            .on_hover_text("Hier kannst du für jede der vier Richtungen selbst festlegen, wie wahrscheinlich sie ist.");
+        if *radio == RadioState::Directional {
+            linked_probability_sliders(ui, direction_probabilities);
+        }
+        ui.radio_value(radio, RadioState::Interacting, "Wechselwirkende Walker")
+            .on_hover_text("Jeder Walker wird vom Schwerpunkt der anderen Walker angezogen oder abgestoßen.");
+        if *radio == RadioState::Interacting {
+            ui.add(
+                egui::Slider::new(coupling, -1.0..=1.0)
+                .text("Kopplung")
+            ).on_hover_text("Positiv: Die Walker ziehen sich an. Negativ: Die Walker stoßen sich ab");
+        }
+        ui.radio_value(radio, RadioState::TrueSelfAvoiding, "Selbstabstoßend")
+            .on_hover_text("Ist diese Option ausgewählt meidet der Walker Felder auf denen er schon oft war. Die Wahrscheinlichkeit ein Feld zu betreten fällt wie exp(-g · Besuche) ab.");
+        ui.radio_value(radio, RadioState::YourFunction, "Deine Funktion")
+            .on_hover_text("Du hast eine eigene Idee für einen Random Walker? Perfekt. Trage die möglichen Schritte in die Tabelle ein, schreibe eine Regel oder programmiere sie selbst und wähle sie hier aus");
+        if *radio == RadioState::YourFunction {
+            ui.horizontal(
+                |ui|
+                {
+                    ui.selectable_value(your_function_mode, YourFunctionMode::Table, "Tabelle");
+                    ui.selectable_value(your_function_mode, YourFunctionMode::Script, "Regel");
+                    ui.selectable_value(your_function_mode, YourFunctionMode::Code, "Code")
+                        .on_hover_text("Deine eigene Funktion in Rust");
+                }
+            );
+            match your_function_mode{
+                YourFunctionMode::Table => {
+                    egui::CollapsingHeader::new("Schritttabelle")
+                        .default_open(true)
+                        .show(
+                            ui,
+                            |ui|
+                            {
+                                if step_table.ui(ui){
+                                    *compiled_step_table = step_table.compile();
+                                }
+                            }
+                        );
+                },
+                YourFunctionMode::Script => {
+                    if ui.add(
+                        egui::TextEdit::multiline(step_script_source)
+                            .code_editor()
+                            .desired_rows(8)
+                            .desired_width(f32::INFINITY)
+                    ).changed(){
+                        *step_script = StepScript::parse(step_script_source);
+                        *script_runtime_error = None;
+                    }
+                    if let Err(error) = step_script{
+                        ui.colored_label(ui.visuals().error_fg_color, error.to_string());
+                    }
+                    if let Some(error) = script_runtime_error{
+                        ui.colored_label(ui.visuals().error_fg_color, format!("Die Walker sind angehalten: {error}"));
+                    }
+                    ui.collapsing("Hilfe", |ui| ui.label(SCRIPT_HELP));
+                },
+                YourFunctionMode::Code => {
+                    ui.label("Die Walker laufen nach der Funktion your_step_function in src/random_walker.rs. Ändere sie und starte das Programm neu. Der Bias-Regler wird ihr als strength_of_bias übergeben");
+                }
+            }
+        }
+        ui.add(
+            egui::Slider::new(strength_of_bias, 0.0..=0.5)
+            .logarithmic(true)
+            .text("Bias")
+            .smallest_positive(0.0005)
+        );
+        if *radio == RadioState::TrueSelfAvoiding {
+            ui.add(
+                egui::Slider::new(repulsion, 0.0..=10.0)
+                .text("Abstoßung g")
+            ).on_hover_text("Je größer g, desto stärker meidet der Walker bereits besuchte Felder");
+            ui.checkbox(one_dimensional, "Nur rechts/links (1D)")
+                .on_hover_text("Der Walker läuft nur entlang der x-Achse");
+        }
+        ui.checkbox(box_enabled, "Kasten")
+            .on_hover_text("Die Walker können den Kasten nicht verlassen. Ein Schritt nach draußen wird zurückgenommen, der Walker bleibt dann stehen");
+        ui.add(
+            egui::Slider::new(box_half_width, 1..=50)
+            .text("Kastengröße L")
+        ).on_hover_text("Der Kasten reicht von -L bis L. Er wird auch für die Abdeckung verwendet, selbst wenn er nicht aktiv ist");
+    }
+}
+
+/// Vier Schieberegler für die Wahrscheinlichkeiten nach rechts, links, oben und unten.
+/// Wird einer verschoben, werden die anderen so angepasst, dass die Summe immer 1 bleibt
+fn linked_probability_sliders(ui: &mut egui::Ui, probabilities: &mut [f64; 4])
+{
+    let names = ["rechts", "links", "oben", "unten"];
+    for i in 0..probabilities.len(){
+        let mut value = probabilities[i];
+        if ui.add(egui::Slider::new(&mut value, 0.0..=1.0).text(names[i])).changed(){
+            let rest_old: f64 = probabilities.iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, p)| p)
+                .sum();
+            let rest_new = 1.0 - value;
+            for (j, p) in probabilities.iter_mut().enumerate(){
+                if j == i {
+                    *p = value;
+                } else if rest_old > 0.0 {
+                    *p *= rest_new / rest_old;
+                } else {
+                    *p = rest_new / 3.0;
+                }
+            }
+        }
+    }
+    if ui.button("Alle gleich").clicked(){
+        *probabilities = [0.25; 4];
+    }
+}
@@ -0,0 +1,303 @@
+use egui::plot::*;
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+
+use crate::ensemble::MAX_WALKER_STEPS_PER_UPDATE;
+use crate::large_deviation::{mutate, rayleigh_log10, replay_distance};
+use crate::power_law::log_axis_label;
+use crate::walk_model::WalkModel;
+
+/// Nach so vielen Zügen wird geprüft, ob das Histogramm flach ist
+const FLATNESS_CHECK_INTERVAL: u64 = 1000;
+/// Breite der Balken von d
+const BIN_WIDTH: f64 = 1.0;
+
+/// Wang-Landau-Sampling der Verteilung des Abstands nach n Schritten.
+///
+/// Statt eine Temperatur vorzugeben, lernt der Algorithmus selbst ein Gewicht
+/// 1 / g(d) für jeden Abstand: Jedes Mal wenn d besucht wird, wird ln g(d) um ln f
+/// erhöht. Oft besuchte Abstände werden dadurch unwahrscheinlicher, bis alle Abstände
+/// gleich oft besucht werden. Ist das Histogramm H(d) flach genug, wird ln f halbiert.
+/// Am Ende ist g(d) proportional zu P(d).
+///
+/// Welche Abstände es überhaupt gibt, wird unterwegs herausgefunden. Ein neu entdeckter
+/// Abstand bekommt das kleinste bisherige ln g, damit er nicht zu lange besucht wird.
+#[derive(Debug, Clone)]
+pub struct WangLandau{
+    pub steps: usize,
+    pub mutations: usize,
+    /// H ist flach, wenn min H >= flatness · mittleres H
+    pub flatness: f64,
+    /// Ist ln f kleiner, ist die Schätzung fertig
+    pub final_ln_f: f64,
+    pub running: bool,
+    model: Option<WalkModel>,
+    rng: Pcg64,
+    numbers: Vec<f64>,
+    bin: usize,
+    ln_g: Vec<f64>,
+    histogram: Vec<u64>,
+    visited: Vec<bool>,
+    pub ln_f: f64,
+    /// Wie oft ln f schon halbiert wurde
+    pub iterations: usize,
+    pub moves: u64,
+    pub accepted: u64,
+    invalid: bool
+}
+
+impl Default for WangLandau{
+    fn default() -> Self {
+        Self{
+            steps: 100,
+            mutations: 2,
+            flatness: 0.8,
+            final_ln_f: 1e-6,
+            running: false,
+            model: None,
+            rng: Pcg64::seed_from_u64(0),
+            numbers: Vec::new(),
+            bin: 0,
+            ln_g: Vec::new(),
+            histogram: Vec::new(),
+            visited: Vec::new(),
+            ln_f: 1.0,
+            iterations: 0,
+            moves: 0,
+            accepted: 0,
+            invalid: false
+        }
+    }
+}
+
+impl WangLandau{
+    pub fn start(&mut self, model: &WalkModel, seed: u64)
+    {
+        *self = Self{
+            steps: self.steps,
+            mutations: self.mutations,
+            flatness: self.flatness,
+            final_ln_f: self.final_ln_f,
+            running: true,
+            model: Some(model.clone()),
+            rng: Pcg64::seed_from_u64(seed),
+            ..Self::default()
+        };
+        let mut model = model.clone();
+        match replay_distance(&mut model, &mut self.numbers, self.steps, &mut self.rng){
+            Some(distance) => {
+                self.bin = self.discover(distance);
+            },
+            None => {
+                self.running = false;
+                self.invalid = true;
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool
+    {
+        self.ln_f < self.final_ln_f
+    }
+
+    pub fn is_busy(&self) -> bool
+    {
+        self.running && self.model.is_some() && !self.is_finished()
+    }
+
+    /// Der Balken von `distance`, der beim ersten Besuch angelegt wird
+    fn discover(&mut self, distance: f64) -> usize
+    {
+        let bin = (distance / BIN_WIDTH) as usize;
+        if bin >= self.ln_g.len() {
+            self.ln_g.resize(bin + 1, 0.0);
+            self.histogram.resize(bin + 1, 0);
+            self.visited.resize(bin + 1, false);
+        }
+        if !self.visited[bin] {
+            let min = self.ln_g.iter()
+                .zip(&self.visited)
+                .filter(|(_, visited)| **visited)
+                .map(|(ln_g, _)| *ln_g)
+                .fold(f64::INFINITY, f64::min);
+            self.ln_g[bin] = if min.is_finite() { min } else { 0.0 };
+            self.visited[bin] = true;
+        }
+        bin
+    }
+
+    /// Das Verhältnis min H / mittleres H über alle bisher gefundenen Abstände
+    pub fn flatness_ratio(&self) -> f64
+    {
+        let visited: Vec<u64> = self.histogram.iter()
+            .zip(&self.visited)
+            .filter(|(_, visited)| **visited)
+            .map(|(h, _)| *h)
+            .collect();
+        if visited.is_empty() {
+            return 0.0;
+        }
+        let mean = visited.iter().sum::<u64>() as f64 / visited.len() as f64;
+        let min = visited.iter().copied().min().unwrap_or(0) as f64;
+        if mean > 0.0 { min / mean } else { 0.0 }
+    }
+
+    pub fn run_pending(&mut self)
+    {
+        if !self.running || self.is_finished() {
+            return;
+        }
+        let Some(mut model) = self.model.take() else {
+            return;
+        };
+        let moves = (MAX_WALKER_STEPS_PER_UPDATE / self.steps).max(1);
+        for _ in 0..moves{
+            let mut proposal = mutate(&self.numbers, self.mutations, &mut self.rng);
+            let Some(distance) = replay_distance(&mut model, &mut proposal, self.steps, &mut self.rng) else {
+                self.running = false;
+                self.invalid = true;
+                break;
+            };
+            let new_bin = self.discover(distance);
+            self.moves += 1;
+            // Annahme mit min(1, g(alt) / g(neu))
+            let ln_ratio = self.ln_g[self.bin] - self.ln_g[new_bin];
+            if ln_ratio >= 0.0 || self.rng.gen::<f64>() < ln_ratio.exp() {
+                self.accepted += 1;
+                self.numbers = proposal;
+                self.bin = new_bin;
+            }
+            self.ln_g[self.bin] += self.ln_f;
+            self.histogram[self.bin] += 1;
+
+            if self.moves % FLATNESS_CHECK_INTERVAL == 0 && self.flatness_ratio() >= self.flatness {
+                self.ln_f /= 2.0;
+                self.iterations += 1;
+                self.histogram.iter_mut().for_each(|h| *h = 0);
+                if self.is_finished() {
+                    self.running = false;
+                    break;
+                }
+            }
+        }
+        self.model = Some(model);
+    }
+
+    /// ln P(d) für alle gefundenen Abstände, normiert auf 1
+    pub fn log_probability(&self) -> Vec<Option<f64>>
+    {
+        let max = self.ln_g.iter()
+            .zip(&self.visited)
+            .filter(|(_, visited)| **visited)
+            .map(|(ln_g, _)| *ln_g)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let norm = max + self.ln_g.iter()
+            .zip(&self.visited)
+            .filter(|(_, visited)| **visited)
+            .map(|(ln_g, _)| (ln_g - max).exp())
+            .sum::<f64>()
+            .ln();
+        self.ln_g.iter()
+            .zip(&self.visited)
+            .map(|(ln_g, visited)| visited.then_some(ln_g - norm))
+            .collect()
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &WalkModel, seed: u64)
+    {
+        ui.horizontal_wrapped(
+            |ui|
+            {
+                ui.label("Schritte n");
+                ui.add_enabled(!self.running, egui::DragValue::new(&mut self.steps).clamp_range(1..=2000));
+                ui.label("Änderungen pro Zug");
+                ui.add(egui::DragValue::new(&mut self.mutations).clamp_range(1..=100));
+                ui.label("Flachheit");
+                ui.add(egui::DragValue::new(&mut self.flatness).clamp_range(0.1..=0.99).speed(0.01))
+                    .on_hover_text("Das Histogramm gilt als flach, wenn jeder Abstand mindestens so oft wie dieser Anteil des Mittelwerts besucht wurde");
+                ui.label("ln f am Ende");
+                ui.add(egui::DragValue::new(&mut self.final_ln_f).clamp_range(1e-12..=0.1).speed(1e-7));
+                if ui.add_enabled(!self.running, egui::Button::new("Starten")).clicked(){
+                    self.start(model, seed);
+                }
+                if self.running {
+                    ui.spinner();
+                    if ui.button("Pause").clicked(){
+                        self.running = false;
+                    }
+                } else if self.model.is_some() && !self.is_finished() && ui.button("Weiter").clicked(){
+                    self.running = true;
+                }
+            }
+        );
+        if self.invalid {
+            ui.colored_label(ui.visuals().error_fg_color, "Die Schrittregel ist ungültig");
+        }
+        if self.model.is_none() {
+            ui.label("Noch keine Daten");
+            return;
+        }
+        let status = if self.is_finished() { "fertig" } else { "läuft" };
+        ui.label(
+            format!(
+                "{status}: ln f = {:.2e} nach {} Halbierungen, Flachheit {:.2} (Ziel {:.2}), {} Abstände gefunden, {} Züge, {:.0} % angenommen",
+                self.ln_f,
+                self.iterations,
+                self.flatness_ratio(),
+                self.flatness,
+                self.visited.iter().filter(|v| **v).count(),
+                self.moves,
+                100.0 * self.accepted as f64 / self.moves.max(1) as f64
+            )
+        ).on_hover_text("Die Schätzung ist erst am Ende genau: ln f ist der Fehler, den ein einzelner Besuch noch in ln g(d) macht");
+
+        let log_p = self.log_probability();
+        let points: Vec<PlotPoint> = log_p.iter()
+            .enumerate()
+            .filter_map(
+                |(bin, p)|
+                {
+                    let d = (bin as f64 + 0.5) * BIN_WIDTH;
+                    p.map(|p| PlotPoint { x: d, y: p / std::f64::consts::LN_10 })
+                }
+            ).collect();
+        let max_d = log_p.len() as f64 * BIN_WIDTH;
+        let rayleigh: Vec<PlotPoint> = (1..=200)
+            .map(|i| max_d * i as f64 / 200.0)
+            .map(|d| PlotPoint { x: d, y: rayleigh_log10(d, self.steps, BIN_WIDTH) })
+            .collect();
+        let bars: Vec<Bar> = self.histogram.iter()
+            .enumerate()
+            .map(|(bin, h)| Bar::new((bin as f64 + 0.5) * BIN_WIDTH, *h as f64).width(BIN_WIDTH))
+            .collect();
+
+        let height = (ui.available_height() * 0.7).max(50.0);
+        Plot::new("wang_landau_plot")
+            .legend(Legend::default())
+            .height(height)
+            .y_axis_formatter(|v, _| log_axis_label(v))
+            .label_formatter(|name, p| format!("{name}\nd = {:.1}\nP = {}", p.x, log_axis_label(p.y)))
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    plot_ui.points(Points::new(PlotPoints::Owned(points)).name("P(d) Wang-Landau").radius(2.5));
+                    plot_ui.line(
+                        Line::new(PlotPoints::Owned(rayleigh))
+                            .name("Gauß-Näherung (Rayleigh)")
+                            .style(LineStyle::dashed_loose())
+                    );
+                }
+            );
+        ui.label("Histogramm H(d) seit der letzten Halbierung von ln f");
+        Plot::new("wang_landau_histogram")
+            .include_y(0.0)
+            .show(
+                ui,
+                |plot_ui|
+                {
+                    plot_ui.bar_chart(BarChart::new(bars).name("H(d)"));
+                }
+            );
+    }
+}
@@ -0,0 +1,159 @@
+use std::f64::consts::PI;
+
+use egui::plot::*;
+
+use crate::ensemble::{curve, histogram};
+use crate::observable::quantile;
+use crate::random_walker::{Position, RandomWalker};
+
+/// Wie viele Balken das Histogramm hat
+const HISTOGRAM_BINS: usize = 40;
+
+/// Der aufsummierte Windungswinkel eines Walkers um den Ursprung.
+///
+/// Bei jedem Schritt wird die Änderung des Polarwinkels addiert, abgebildet auf [-π, π].
+/// Im Ursprung ist der Winkel nicht definiert, dort bleibt er einfach stehen.
+/// Gezählt wird erst ab der ersten Position außerhalb des Ursprungs, dort ist der
+/// Winkel 0 - egal in welche Richtung der Walker losgeht.
+///
+/// Läuft der Walker durch den Ursprung hindurch, wird die Änderung zur letzten
+/// Position außerhalb des Ursprungs gemessen. Ist sie genau ±π (der Walker kommt
+/// auf der anderen Seite wieder heraus), ist nicht zu entscheiden, wie herum er
+/// gelaufen ist. Dann zählt je die Hälfte in beide Richtungen, also gar nichts.
+/// So hat der Winkel keine bevorzugte Richtung.
+///
+/// Beispiel:
+/// ```
+/// use girls_day::winding::Winding;
+/// use girls_day::random_walker::Position;
+/// use std::f64::consts::PI;
+/// let wind = |path: &[(i32, i32)]| {
+///     let mut winding = Winding::default();
+///     path.iter().for_each(|&(x, y)| winding.add(&Position { x, y }));
+///     winding.angle()
+/// };
+/// // Einmal im Kreis um den Ursprung herum
+/// let circle = [(0, 0), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1), (1, 0)];
+/// assert!((wind(&circle) - 2.0 * PI).abs() < 1e-12);
+/// // Der erste Schritt zählt nicht, egal wohin
+/// assert_eq!(wind(&[(0, 0), (0, -1)]), 0.0);
+/// assert_eq!(wind(&[(0, 0), (-1, 0)]), 0.0);
+/// // Durch den Ursprung hindurch: weder mit noch gegen den Uhrzeigersinn
+/// assert_eq!(wind(&[(0, 0), (1, 0), (0, 0), (-1, 0)]), 0.0);
+/// assert_eq!(wind(&[(0, 0), (-1, 0), (0, 0), (1, 0)]), 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Winding{
+    angle: f64,
+    /// Der Polarwinkel der letzten Position außerhalb des Ursprungs
+    last_polar: Option<f64>
+}
+
+impl Winding{
+    pub fn add(&mut self, pos: &Position)
+    {
+        if pos.x == 0 && pos.y == 0 {
+            return;
+        }
+        let polar = (pos.y as f64).atan2(pos.x as f64);
+        if let Some(last_polar) = self.last_polar {
+            let delta = (polar - last_polar).rem_euclid(2.0 * PI);
+            // Genau gegenüber: +π und -π sind gleich wahrscheinlich
+            if (delta - PI).abs() > 1e-9 {
+                self.angle += if delta > PI { delta - 2.0 * PI } else { delta };
+            }
+        }
+        self.last_polar = Some(polar);
+    }
+
+    /// Wie weit sich der Walker bisher um den Ursprung gedreht hat, im Bogenmaß.
+    /// Positiv ist gegen den Uhrzeigersinn
+    pub fn angle(&self) -> f64
+    {
+        self.angle
+    }
+}
+
+/// Spitzers Gesetz: Für die Brownsche Bewegung ist 2θ / ln(t) für große t
+/// Cauchy-verteilt. Das ist die Dichte von θ selbst
+pub fn spitzer_density(theta: f64, t: f64) -> f64
+{
+    let a = t.ln() / 2.0;
+    a / (PI * (a * a + theta * theta))
+}
+
+/// Für Walker auf dem Gitter, die dem Ursprung nicht beliebig nahe kommen können,
+/// ist 2θ / ln(t) stattdessen sech-verteilt mit Dichte 1/2 · sech(π x / 2) (Bélisle)
+pub fn lattice_density(theta: f64, t: f64) -> f64
+{
+    let a = t.ln() / 2.0;
+    0.5 / (a * (PI * theta / (2.0 * a)).cosh())
+}
+
+/// Histogramm der Windungswinkel aller Walker beim aktuellen Schritt
+#[derive(Debug, Clone, Default)]
+pub struct WindingView{
+    /// Statt θ wird 2θ / ln(t) gezeigt, dann hängt die Vorhersage nicht mehr von t ab
+    pub scaled: bool
+}
+
+impl WindingView{
+    pub fn ui(&mut self, ui: &mut egui::Ui, walkers: &[RandomWalker])
+    {
+        ui.checkbox(&mut self.scaled, "Skaliert: 2θ / ln(t)")
+            .on_hover_text("Teilt den Winkel durch ln(t)/2. Dann sollten die Histogramme zu allen Zeiten ungefähr gleich aussehen");
+        let steps = walkers[0].history.len();
+        let mut angles: Vec<f64> = walkers.iter()
+            .map(|walker| walker.history.winding.angle())
+            .collect();
+        angles.sort_unstable_by(f64::total_cmp);
+        let turns = angles.iter().map(|a| a.abs()).sum::<f64>() / (2.0 * PI * angles.len() as f64);
+        ui.label(
+            format!(
+                "{} Walker nach {steps} Schritten, im Mittel {turns:.2} Umdrehungen (Betrag)",
+                angles.len()
+            )
+        ).on_hover_text("Der Windungswinkel zählt, wie oft sich der Walker um den Ursprung gedreht hat. Positiv ist gegen den Uhrzeigersinn");
+        if steps < 3 {
+            ui.label("Noch keine Daten");
+            return;
+        }
+
+        let t = steps as f64;
+        // Mit t = e² ist ln(t)/2 = 1, die Dichten sind dann die von 2θ / ln(t)
+        let (scale, t_curve) = if self.scaled {
+            (2.0 / t.ln(), std::f64::consts::E.powi(2))
+        } else {
+            (1.0, t)
+        };
+        angles.iter_mut().for_each(|a| *a *= scale);
+        // Die Verteilung hat sehr breite Ränder, daher nur bis zu den 2%- und 98%-Quantilen
+        let max = quantile(&angles, 0.02).abs()
+            .max(quantile(&angles, 0.98).abs())
+            .max(PI * scale);
+        let bin_width = 2.0 * max / HISTOGRAM_BINS as f64;
+        let bars = histogram(&angles, -max, bin_width, HISTOGRAM_BINS);
+        let spitzer = curve(-max, max, |theta| spitzer_density(theta, t_curve));
+        let lattice = curve(-max, max, |theta| lattice_density(theta, t_curve));
+
+        let mut plot = Plot::new(if self.scaled { "winding_histogram_scaled" } else { "winding_histogram" })
+            .legend(Legend::default())
+            .include_y(0.0);
+        if !self.scaled {
+            plot = plot.label_formatter(|name, p| format!("{name}\nθ = {:.2} ({:.2} Umdrehungen)", p.x, p.x / (2.0 * PI)));
+        }
+        plot.show(
+            ui,
+            |plot_ui|
+            {
+                plot_ui.bar_chart(BarChart::new(bars).name("Walker"));
+                plot_ui.line(Line::new(PlotPoints::Owned(spitzer)).name("Spitzer: Cauchy mit Breite ln(t)/2"));
+                plot_ui.line(
+                    Line::new(PlotPoints::Owned(lattice))
+                        .name("Gitter: sech-Verteilung")
+                        .style(LineStyle::dashed_loose())
+                );
+            }
+        );
+    }
+}